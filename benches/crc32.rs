@@ -0,0 +1,73 @@
+use std::str::FromStr;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+// The crate is a binary, so the CRC code and what it depends on are
+// pulled in directly. Their unit tests don't run here, which leaves
+// the imports of the test modules unused.
+#[allow(dead_code, unused_imports)]
+#[path = "../src/chunk_type.rs"]
+mod chunk_type;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/crc32.rs"]
+mod crc32;
+#[allow(dead_code, unused_imports)]
+#[path = "../src/errors.rs"]
+mod errors;
+
+use chunk_type::ChunkType;
+
+/// IDAT-sized inputs, from a small chunk up to a multi-megabyte one.
+const SIZES: [usize; 4] = [8 << 10, 64 << 10, 1 << 20, 8 << 20];
+
+fn idat_data(size: usize) -> Vec<u8> {
+    // Anything that isn't all zeroes will do.
+    (0..size).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+fn bench_calculate_crc(c: &mut Criterion) {
+    let chunk_type = ChunkType::from_str("IDAT").unwrap();
+    let mut group = c.benchmark_group("calculate_crc");
+
+    for size in SIZES {
+        let data = idat_data(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &data, |b, data| {
+            b.iter(|| crc32::calculate_crc(black_box(&chunk_type), black_box(data)))
+        });
+    }
+    group.finish();
+}
+
+type UpdateCrc = fn(u32, &[u8]) -> u32;
+
+fn bench_update_crc_variants(c: &mut Criterion) {
+    let data = idat_data(1 << 20);
+    let mut group = c.benchmark_group("update_crc_1MiB");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    let variants: [(&str, UpdateCrc); 4] = [
+        ("bytewise", crc32::update_crc_bytewise),
+        ("slice_by_8", crc32::update_crc_slice_by_8),
+        ("slice_by_16", crc32::update_crc_slice_by_16),
+        ("dispatch", crc32::update_crc),
+    ];
+    for (name, update) in variants {
+        group.bench_function(name, |b| {
+            b.iter(|| update(black_box(crc32::CRC_INIT), black_box(&data)))
+        });
+    }
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if crc32::pclmulqdq::is_supported() {
+        group.bench_function("pclmulqdq", |b| {
+            // SAFETY: the required CPU features were just detected.
+            b.iter(|| unsafe {
+                crc32::pclmulqdq::update_crc(black_box(crc32::CRC_INIT), black_box(&data))
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_calculate_crc, bench_update_crc_variants);
+criterion_main!(benches);
@@ -1 +1,359 @@
+use std::path::PathBuf;
 
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::lint::Severity;
+
+/// Hide secret messages inside PNG files.
+#[derive(Debug, Parser)]
+#[command(name = "hushpong", version, about)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Command,
+}
+
+/// The subcommands `hushpong` understands.
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Hides a message inside a PNG file as a new chunk.
+    Encode(EncodeArgs),
+    /// Prints the message stored in the first chunk of the given type.
+    Decode(DecodeArgs),
+    /// Removes the first chunk of the given type from a PNG file.
+    Remove(RemoveArgs),
+    /// Prints every chunk of a PNG file.
+    Print(PrintArgs),
+    /// Fixes bad CRCs, length fields and a missing IEND, and drops trailing junk.
+    Repair(RepairArgs),
+    /// Checks PNG files against the spec, for use in CI.
+    Lint(LintArgs),
+    /// Lists, reads, sets or removes tEXt, zTXt and iTXt entries.
+    Text(TextArgs),
+    /// Lists the palette with each entry's alpha, or changes one entry.
+    Palette(PaletteArgs),
+    /// Shows, extracts or embeds the ICC color profile.
+    Icc(IccArgs),
+    /// Lists the EXIF tags, or strips the EXIF data.
+    Exif(ExifArgs),
+    /// Shows the resolution, or sets it in dots per inch.
+    Dpi(DpiArgs),
+    /// Removes metadata, keeping the chunks that affect rendering.
+    Strip(StripArgs),
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct EncodeArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// The 4-letter chunk type code to store the message under, e.g. `ruSt`.
+    pub(crate) chunk_type: String,
+    /// The message to hide.
+    pub(crate) message: String,
+    /// Where to write the result. Defaults to overwriting `file`.
+    pub(crate) output: Option<PathBuf>,
+    /// Set the tIME chunk to the current time.
+    #[arg(long)]
+    pub(crate) touch: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct DecodeArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// The 4-letter chunk type code the message is stored under.
+    pub(crate) chunk_type: String,
+    /// Skip chunks with a bad CRC or chunk type instead of failing.
+    #[arg(long)]
+    pub(crate) lenient: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct RemoveArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// The 4-letter chunk type code of the chunk to remove.
+    pub(crate) chunk_type: String,
+    /// Remove every chunk of the type, not just the first.
+    #[arg(long)]
+    pub(crate) all: bool,
+    /// Set the tIME chunk to the current time.
+    #[arg(long)]
+    pub(crate) touch: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct PrintArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// List chunks with a bad CRC or chunk type instead of failing.
+    #[arg(long)]
+    pub(crate) lenient: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct RepairArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// Where to write the result. Defaults to overwriting `file`.
+    pub(crate) output: Option<PathBuf>,
+    /// Only report what would be changed.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct LintArgs {
+    /// Paths to the PNG files.
+    #[arg(required = true)]
+    pub(crate) files: Vec<PathBuf>,
+    /// How to print the findings.
+    #[arg(long, value_enum, default_value_t = LintFormat::Text)]
+    pub(crate) format: LintFormat,
+    /// Exit with an error if any finding is at least this severe.
+    #[arg(long, value_enum, default_value_t = Severity::Error)]
+    pub(crate) fail_on: Severity,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct TextArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// The keyword to read, set or remove. Lists every entry if left out.
+    pub(crate) keyword: Option<String>,
+    /// Store this value under the keyword, replacing any existing entries.
+    #[arg(long, requires = "keyword", conflicts_with = "remove")]
+    pub(crate) set: Option<String>,
+    /// The language of the value, e.g. `ja` or `en-GB`. Stores it in iTXt.
+    #[arg(long, value_name = "TAG", requires = "set")]
+    pub(crate) lang: Option<String>,
+    /// The keyword translated into the language of the value. Stores it in iTXt.
+    #[arg(long, value_name = "KEYWORD", requires = "set")]
+    pub(crate) translated: Option<String>,
+    /// Remove every entry with the keyword.
+    #[arg(long, requires = "keyword")]
+    pub(crate) remove: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct PaletteArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// The entry to read or change, counting from 0. Lists every entry if left out.
+    pub(crate) index: Option<usize>,
+    /// Change the entry's color, given as `RRGGBB` hex.
+    #[arg(long, requires = "index", value_name = "RRGGBB")]
+    pub(crate) set: Option<String>,
+    /// Change the entry's alpha, from 0 (transparent) to 255 (opaque).
+    #[arg(long, requires = "index")]
+    pub(crate) alpha: Option<u8>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct IccArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// Write the embedded profile to this `.icc` file.
+    #[arg(long, value_name = "PATH", conflicts_with = "embed")]
+    pub(crate) extract: Option<PathBuf>,
+    /// Embed the profile in this `.icc` file, replacing any iCCP or sRGB chunk.
+    #[arg(long, value_name = "PATH")]
+    pub(crate) embed: Option<PathBuf>,
+    /// The name to store the embedded profile under.
+    #[arg(long, requires = "embed")]
+    pub(crate) name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct ExifArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// Remove every eXIf chunk instead of listing the tags.
+    #[arg(long)]
+    pub(crate) strip: bool,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct DpiArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// Set the horizontal and vertical resolution to this many dots per inch.
+    #[arg(long, value_name = "DPI")]
+    pub(crate) set: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+pub(crate) struct StripArgs {
+    /// Path to the PNG file.
+    pub(crate) file: PathBuf,
+    /// Where to write the result. Defaults to overwriting `file`.
+    pub(crate) output: Option<PathBuf>,
+    /// An ancillary chunk type to keep as well, e.g. `tEXt`.
+    #[arg(long, value_name = "CHUNK_TYPE")]
+    pub(crate) keep: Vec<String>,
+    /// An ancillary chunk type to remove even if it's kept by default.
+    #[arg(long, value_name = "CHUNK_TYPE")]
+    pub(crate) drop: Vec<String>,
+    /// Only list what would be kept and removed.
+    #[arg(long)]
+    pub(crate) dry_run: bool,
+}
+
+/// The output formats of the `lint` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum LintFormat {
+    /// One line per finding.
+    Text,
+    /// A JSON array with one object per file.
+    Json,
+}
+
+#[cfg(test)]
+mod args_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_encode_without_output() {
+        let cli = Cli::try_parse_from(["hushpong", "encode", "a.png", "ruSt", "hello"]).unwrap();
+        match cli.command {
+            Command::Encode(args) => {
+                assert_eq!(args.file, PathBuf::from("a.png"));
+                assert_eq!(args.chunk_type, "ruSt");
+                assert_eq!(args.message, "hello");
+                assert!(args.output.is_none());
+                assert!(!args.touch);
+            }
+            _ => panic!("expected the encode subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_encode_with_output() {
+        let cli =
+            Cli::try_parse_from(["hushpong", "encode", "a.png", "ruSt", "hello", "b.png"]).unwrap();
+        match cli.command {
+            Command::Encode(args) => assert_eq!(args.output, Some(PathBuf::from("b.png"))),
+            _ => panic!("expected the encode subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_touch_flag() {
+        let cli = Cli::try_parse_from(["hushpong", "remove", "--touch", "a.png", "ruSt"]).unwrap();
+        match cli.command {
+            Command::Remove(args) => {
+                assert!(args.touch);
+                assert!(!args.all);
+            }
+            _ => panic!("expected the remove subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_lenient_flag() {
+        let cli = Cli::try_parse_from(["hushpong", "print", "--lenient", "a.png"]).unwrap();
+        match cli.command {
+            Command::Print(args) => assert!(args.lenient),
+            _ => panic!("expected the print subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_lint() {
+        let cli = Cli::try_parse_from([
+            "hushpong",
+            "lint",
+            "--format",
+            "json",
+            "--fail-on",
+            "warning",
+            "a.png",
+            "b.png",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Lint(args) => {
+                assert_eq!(args.files.len(), 2);
+                assert_eq!(args.format, LintFormat::Json);
+                assert_eq!(args.fail_on, Severity::Warning);
+            }
+            _ => panic!("expected the lint subcommand"),
+        }
+        assert!(Cli::try_parse_from(["hushpong", "lint"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let cli =
+            Cli::try_parse_from(["hushpong", "text", "a.png", "Title", "--set", "Hi"]).unwrap();
+        match cli.command {
+            Command::Text(args) => {
+                assert_eq!(args.keyword.as_deref(), Some("Title"));
+                assert_eq!(args.set.as_deref(), Some("Hi"));
+                assert!(!args.remove);
+            }
+            _ => panic!("expected the text subcommand"),
+        }
+        assert!(Cli::try_parse_from(["hushpong", "text", "a.png", "--remove"]).is_err());
+        assert!(Cli::try_parse_from(["hushpong", "text", "a.png", "T", "--lang", "de"]).is_err());
+        assert!(
+            Cli::try_parse_from(["hushpong", "text", "a.png", "T", "--set", "x", "--remove"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_icc() {
+        let cli = Cli::try_parse_from(["hushpong", "icc", "a.png", "--embed", "p.icc"]).unwrap();
+        match cli.command {
+            Command::Icc(args) => {
+                assert_eq!(args.embed, Some(PathBuf::from("p.icc")));
+                assert!(args.extract.is_none());
+                assert!(args.name.is_none());
+            }
+            _ => panic!("expected the icc subcommand"),
+        }
+        assert!(Cli::try_parse_from(["hushpong", "icc", "a.png", "--name", "P3"]).is_err());
+        assert!(Cli::try_parse_from([
+            "hushpong",
+            "icc",
+            "a.png",
+            "--embed",
+            "p.icc",
+            "--extract",
+            "q.icc"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_exif() {
+        let cli = Cli::try_parse_from(["hushpong", "exif", "--strip", "a.png"]).unwrap();
+        match cli.command {
+            Command::Exif(args) => assert!(args.strip),
+            _ => panic!("expected the exif subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_strip() {
+        let cli = Cli::try_parse_from([
+            "hushpong", "strip", "a.png", "--keep", "tEXt", "--keep", "bKGD", "--drop", "pHYs",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Strip(args) => {
+                assert_eq!(args.keep, ["tEXt", "bKGD"]);
+                assert_eq!(args.drop, ["pHYs"]);
+                assert!(args.output.is_none());
+                assert!(!args.dry_run);
+            }
+            _ => panic!("expected the strip subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_parse_missing_argument() {
+        let cli = Cli::try_parse_from(["hushpong", "decode", "a.png"]);
+        assert!(cli.is_err());
+    }
+}
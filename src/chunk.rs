@@ -1,4 +1,4 @@
-use std::io::{BufReader, Read};
+use std::io::Read;
 
 use crate::{chunk_type::ChunkType, errors::Expectations};
 
@@ -31,6 +31,9 @@ pub(crate) struct Chunk {
 }
 
 impl Chunk {
+    /// The largest value the length field may hold, 2^31-1.
+    pub(crate) const MAX_LENGTH: u32 = (1 << 31) - 1;
+
     /// Creates a new Chunk with the specified chunk type
     /// and chunk data.
     pub(crate) fn new(chunk_type: ChunkType, data: Vec<u8>) -> Self {
@@ -59,6 +62,11 @@ impl Chunk {
         &self.chunk_type
     }
 
+    /// Returns the data bytes of the chunk.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.chunk_data
+    }
+
     /// Returns the `Chunk`s data as a string
     pub(crate) fn data_as_string(&self) -> Result<String, PngError> {
         let stringified_data = String::from_utf8(self.chunk_data.clone())?;
@@ -66,6 +74,7 @@ impl Chunk {
     }
 
     /// Returns the `Chunk` as a vector of bytes.
+    #[cfg(test)]
     pub(crate) fn as_bytes(&self) -> Vec<u8> {
         let chunk_bytes: Vec<u8> = self
             .length()
@@ -78,20 +87,14 @@ impl Chunk {
             .collect();
         chunk_bytes
     }
-}
-
-impl TryFrom<&[u8]> for Chunk {
-    type Error = PngError;
 
-    /// Tries to produces a valid `Chunk` from a slice of bytes.
-    // The `value` contains the 4-byte data length type,
+    /// Reads a single valid `Chunk` from `reader`.
+    // The reader yields the 4-byte data length type,
     // the 4-byte chunk type,
     // the unspecified amount of chunk data, and
     // the 4-byte CRC that's calculated based on the chunk
     // type and chunk data.
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        // Create a reader to read from the byte slice
-        let mut reader = BufReader::new(value);
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> Result<Self, PngError> {
         // A 4-byte buffer to write to from the reader
         let mut buffer: [u8; 4] = [0; 4];
         // Get the chunk's length field
@@ -102,11 +105,33 @@ impl TryFrom<&[u8]> for Chunk {
         reader.read_exact(&mut buffer)?;
         let chunk_type = ChunkType::try_from(buffer)?;
 
-        // Get the chunk data
-        let mut chunk_data = vec![0; usize::try_from(chunk_length)?];
-        reader.read_exact(&mut chunk_data)?;
+        Self::read_body(reader, chunk_length, chunk_type)
+    }
+
+    /// Reads the data and CRC of a `Chunk` whose length field and
+    /// chunk type have already been consumed from `reader`.
+    pub(crate) fn read_body<R: Read>(
+        reader: &mut R,
+        chunk_length: u32,
+        chunk_type: ChunkType,
+    ) -> Result<Self, PngError> {
+        if chunk_length > Self::MAX_LENGTH {
+            return Err(PngError::ChunkLengthTooLarge(chunk_length));
+        }
+
+        // Get the chunk data. `take` stops a bogus length
+        // from allocating more than the reader actually holds.
+        let expected_length = usize::try_from(chunk_length)?;
+        let mut chunk_data = Vec::new();
+        reader
+            .take(u64::from(chunk_length))
+            .read_to_end(&mut chunk_data)?;
+        if chunk_data.len() != expected_length {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
 
         // Get CRC
+        let mut buffer: [u8; 4] = [0; 4];
         reader.read_exact(&mut buffer)?;
         let received_crc = u32::from_be_bytes(buffer);
 
@@ -114,13 +139,22 @@ impl TryFrom<&[u8]> for Chunk {
 
         (received_crc == actual_crc)
             .then_some(Self::new(chunk_type, chunk_data))
-            .ok_or(Self::Error::InvalidCrc(Expectations {
+            .ok_or(PngError::InvalidCrc(Expectations {
                 got: received_crc,
                 expected: actual_crc,
             }))
     }
 }
 
+impl TryFrom<&[u8]> for Chunk {
+    type Error = PngError;
+
+    /// Tries to produces a valid `Chunk` from a slice of bytes.
+    fn try_from(mut value: &[u8]) -> Result<Self, Self::Error> {
+        Self::read_from(&mut value)
+    }
+}
+
 #[cfg(test)]
 mod chunk_tests {
     use super::*;
@@ -230,11 +264,7 @@ mod chunk_tests {
         let data_length: u32 = 35;
         let crc: u32 = 2591807180;
         let chunk_type = ChunkType::from_str("RuSt").unwrap();
-        let data: Vec<u8> = "My life is like an eternal night..."
-            .as_bytes()
-            .into_iter()
-            .copied()
-            .collect();
+        let data: Vec<u8> = "My life is like an eternal night...".as_bytes().to_vec();
 
         let chunk_bytes_expected: Vec<u8> = data_length
             .to_be_bytes()
@@ -2,7 +2,7 @@ use crate::errors::PngError;
 use std::fmt::Display;
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 /// The 4-byte chunk type code of a PNG file.
 pub(crate) struct ChunkType([u8; 4]);
 
@@ -167,7 +167,7 @@ mod chunktype_tests {
         let are_chunks_equal = chunk_type_1 == chunk_type_2;
 
         assert_eq!(chunk_to_string, String::from("RuSt"));
-        assert_eq!(are_chunks_equal, true);
+        assert!(are_chunks_equal);
     }
 
     #[test]
@@ -0,0 +1,224 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::PngError,
+    typed_chunk::{be_u32, TypedChunk},
+};
+
+/// The image gamma (`gAMA`), stored times 100000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Gamma {
+    /// The gamma times 100000, e.g. 45455 for 1/2.2.
+    pub(crate) value: u32,
+}
+
+impl Gamma {
+    /// Returns the gamma as a fraction, e.g. 0.45455.
+    pub(crate) fn gamma(&self) -> f64 {
+        f64::from(self.value) / 100_000.0
+    }
+}
+
+impl TypedChunk for Gamma {
+    const CHUNK_TYPE: [u8; 4] = *b"gAMA";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, 4)?;
+        let value = be_u32(data, 0);
+        if value == 0 {
+            return Err(Self::invalid("gamma must not be zero"));
+        }
+        Ok(Self { value })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.value.to_be_bytes().to_vec()
+    }
+}
+
+impl Display for Gamma {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "gamma {}", self.gamma())
+    }
+}
+
+/// The CIE 1931 x,y chromaticities of the white point and
+/// the red, green and blue primaries (`cHRM`), each stored times 100000.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Chromaticities {
+    pub(crate) white_point: (u32, u32),
+    pub(crate) red: (u32, u32),
+    pub(crate) green: (u32, u32),
+    pub(crate) blue: (u32, u32),
+}
+
+impl Chromaticities {
+    /// The length of the cHRM chunk data.
+    pub(crate) const LENGTH: usize = 32;
+
+    fn points(&self) -> [(u32, u32); 4] {
+        [self.white_point, self.red, self.green, self.blue]
+    }
+}
+
+impl TypedChunk for Chromaticities {
+    const CHUNK_TYPE: [u8; 4] = *b"cHRM";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, Self::LENGTH)?;
+        let point = |index: usize| (be_u32(data, index * 8), be_u32(data, index * 8 + 4));
+        Ok(Self {
+            white_point: point(0),
+            red: point(1),
+            green: point(2),
+            blue: point(3),
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.points()
+            .iter()
+            .flat_map(|&(x, y)| [x, y])
+            .flat_map(u32::to_be_bytes)
+            .collect()
+    }
+}
+
+impl Display for Chromaticities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = ["white point", "red", "green", "blue"];
+        for (index, (name, (x, y))) in names.iter().zip(self.points()).enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            let (x, y) = (f64::from(x) / 100_000.0, f64::from(y) / 100_000.0);
+            write!(f, "{name} ({x}, {y})")?;
+        }
+        Ok(())
+    }
+}
+
+/// How to map colors outside of the output device's gamut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RenderingIntent {
+    Perceptual = 0,
+    RelativeColorimetric = 1,
+    Saturation = 2,
+    AbsoluteColorimetric = 3,
+}
+
+/// Marks the image as being in the sRGB color space (`sRGB`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Srgb {
+    pub(crate) rendering_intent: RenderingIntent,
+}
+
+impl TypedChunk for Srgb {
+    const CHUNK_TYPE: [u8; 4] = *b"sRGB";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, 1)?;
+        let rendering_intent = match data[0] {
+            0 => RenderingIntent::Perceptual,
+            1 => RenderingIntent::RelativeColorimetric,
+            2 => RenderingIntent::Saturation,
+            3 => RenderingIntent::AbsoluteColorimetric,
+            intent => {
+                return Err(Self::invalid(format_args!(
+                    "unknown rendering intent {intent}"
+                )))
+            }
+        };
+        Ok(Self { rendering_intent })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        vec![self.rendering_intent as u8]
+    }
+}
+
+impl Display for Srgb {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let intent = match self.rendering_intent {
+            RenderingIntent::Perceptual => "perceptual",
+            RenderingIntent::RelativeColorimetric => "relative colorimetric",
+            RenderingIntent::Saturation => "saturation",
+            RenderingIntent::AbsoluteColorimetric => "absolute colorimetric",
+        };
+        write!(f, "sRGB, {intent} rendering intent")
+    }
+}
+
+#[cfg(test)]
+mod colorimetry_tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{chunk::Chunk, chunk_type::ChunkType, png::Png};
+
+    #[test]
+    fn test_gamma() {
+        let gamma = Gamma::from_data(&[0, 0, 0xB1, 0x8F]).unwrap();
+        assert_eq!(gamma.value, 45455);
+        assert_eq!(gamma.gamma(), 0.45455);
+        assert_eq!(gamma.to_string(), "gamma 0.45455");
+        assert_eq!(Gamma::from_chunk(&gamma.to_chunk()).unwrap(), gamma);
+    }
+
+    #[test]
+    fn test_gamma_rejects_bad_data() {
+        assert!(Gamma::from_data(&[0, 0, 0, 0]).is_err());
+        assert!(Gamma::from_data(&[0, 0, 0xB1]).is_err());
+    }
+
+    fn srgb_chromaticities() -> Chromaticities {
+        Chromaticities {
+            white_point: (31270, 32900),
+            red: (64000, 33000),
+            green: (30000, 60000),
+            blue: (15000, 6000),
+        }
+    }
+
+    #[test]
+    fn test_chromaticities_round_trip() {
+        let chromaticities = srgb_chromaticities();
+        let data = chromaticities.to_data();
+        assert_eq!(data.len(), 32);
+        assert_eq!(data[..8], [0, 0, 0x7A, 0x26, 0, 0, 0x80, 0x84]);
+        assert_eq!(Chromaticities::from_data(&data).unwrap(), chromaticities);
+        assert!(Chromaticities::from_data(&data[1..]).is_err());
+    }
+
+    #[test]
+    fn test_chromaticities_display() {
+        assert_eq!(
+            srgb_chromaticities().to_string(),
+            "white point (0.3127, 0.329), red (0.64, 0.33), green (0.3, 0.6), blue (0.15, 0.06)"
+        );
+    }
+
+    #[test]
+    fn test_srgb() {
+        let srgb = Srgb::from_data(&[1]).unwrap();
+        assert_eq!(srgb.rendering_intent, RenderingIntent::RelativeColorimetric);
+        assert_eq!(srgb.to_data(), [1]);
+        assert_eq!(
+            srgb.to_string(),
+            "sRGB, relative colorimetric rendering intent"
+        );
+        assert!(Srgb::from_data(&[4]).is_err());
+        assert!(Srgb::from_data(&[0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_find_through_png() {
+        let png = Png::from_chunks(vec![
+            Gamma { value: 100_000 }.to_chunk(),
+            Chunk::new(ChunkType::from_str("sRGB").unwrap(), vec![9]),
+        ]);
+        assert_eq!(png.find::<Gamma>().unwrap().unwrap().gamma(), 1.0);
+        assert!(png.find::<Srgb>().unwrap().is_err());
+        assert!(png.find::<Chromaticities>().is_none());
+    }
+}
@@ -1 +1,1059 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
 
+use crate::args::{
+    Command, DecodeArgs, DpiArgs, EncodeArgs, ExifArgs, IccArgs, LintArgs, LintFormat, PaletteArgs,
+    PrintArgs, RemoveArgs, RepairArgs, StripArgs, TextArgs,
+};
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::colorimetry::{Chromaticities, Gamma, Srgb};
+use crate::dimensions::PhysicalDimensions;
+use crate::errors::PngError;
+use crate::exif::ExifChunk;
+use crate::icc::IccProfileChunk;
+use crate::ihdr::Ihdr;
+use crate::lint::{json_string, Finding, Severity};
+use crate::palette::{Histogram, Palette, PaletteEntry, SuggestedPalette, Transparency};
+use crate::png::Png;
+use crate::png_reader::PngReader;
+use crate::recovery::{CorruptChunkPolicy, RecoveredPng};
+use crate::samples::{Background, SignificantBits};
+use crate::strip::StripPolicy;
+use crate::text::{ITxtChunk, TextChunk, TextKind, ZTxtChunk};
+use crate::time::TimeChunk;
+use crate::typed_chunk::{DependentChunk, TypedChunk};
+use crate::Result;
+
+/// Runs the given subcommand.
+pub(crate) fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Encode(args) => encode(args),
+        Command::Decode(args) => decode(args),
+        Command::Remove(args) => remove(args),
+        Command::Print(args) => print(args),
+        Command::Repair(args) => repair(args),
+        Command::Lint(args) => lint(args),
+        Command::Text(args) => text(args),
+        Command::Palette(args) => palette(args),
+        Command::Icc(args) => icc(args),
+        Command::Exif(args) => exif(args),
+        Command::Dpi(args) => dpi(args),
+        Command::Strip(args) => strip(args),
+    }
+}
+
+/// Inserts a new chunk holding the message before IEND and writes the PNG out.
+///
+/// Files without a valid IHDR are rejected, since they aren't images.
+/// With `--touch`, tIME is set to the current time.
+fn encode(args: EncodeArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    png.ihdr()?;
+    let chunk_type = ChunkType::from_str(&args.chunk_type)?;
+    if !chunk_type.is_valid() {
+        return Err(PngError::InvalidReservedBit(chunk_type.to_string()));
+    }
+    png.insert_before_iend(Chunk::new(chunk_type, args.message.into_bytes()));
+    if args.touch {
+        png.touch()?;
+    }
+
+    let output = args.output.as_deref().unwrap_or(&args.file);
+    png.save(output)
+}
+
+/// Prints the message stored in the first chunk of the given type.
+///
+/// The file is streamed, so reading stops as soon as the chunk is found.
+/// In lenient mode the whole file is read so damaged chunks can be skipped.
+fn decode(args: DecodeArgs) -> Result<()> {
+    if args.lenient {
+        let recovered = read_png_lenient(&args.file, CorruptChunkPolicy::Skip)?;
+        let (_, chunk) = recovered
+            .png
+            .search_chunk(&args.chunk_type)
+            .ok_or(PngError::ChunkNotFound)?;
+        println!("{}", chunk.data_as_string()?);
+        return Ok(());
+    }
+
+    let file = File::open(&args.file).map_err(|source| PngError::FileAccess {
+        path: args.file.clone(),
+        source,
+    })?;
+    let mut reader = PngReader::new(BufReader::new(file))?;
+    let chunk = reader
+        .find(|chunk| {
+            chunk.as_ref().map_or(true, |chunk| {
+                chunk.chunk_type().to_string() == args.chunk_type
+            })
+        })
+        .ok_or(PngError::ChunkNotFound)??;
+    println!("{}", chunk.data_as_string()?);
+    Ok(())
+}
+
+/// Removes the first chunk of the given type, or with `--all` every one,
+/// and writes the PNG back.
+/// With `--touch`, tIME is set to the current time.
+fn remove(args: RemoveArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let removed = if args.all {
+        png.remove_chunks(&args.chunk_type)?.len()
+    } else {
+        png.remove_chunk(&args.chunk_type)?;
+        1
+    };
+    if args.touch {
+        png.touch()?;
+    }
+    png.save(&args.file)?;
+    println!("removed {removed} '{}' chunk(s)", args.chunk_type);
+    Ok(())
+}
+
+/// Prints the type, length, CRC and property bits of every chunk in the PNG.
+///
+/// In lenient mode damaged chunks are listed in place, numbered by
+/// their position in the file like every other chunk.
+fn print(args: PrintArgs) -> Result<()> {
+    if !args.lenient {
+        let png = read_png(&args.file)?;
+        let context = ImageContext::of(&png);
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            print_chunk(index, chunk, &context);
+        }
+        return Ok(());
+    }
+
+    let recovered = read_png_lenient(&args.file, CorruptChunkPolicy::Keep)?;
+    let context = ImageContext::of(&recovered.png);
+    let mut chunks = recovered.png.chunks().iter();
+    let mut corrupt_chunks = recovered.corrupt_chunks.iter().peekable();
+    for index in 0.. {
+        match corrupt_chunks.next_if(|corrupt| corrupt.index == index) {
+            Some(corrupt) => println!(
+                "#{index} {} length={} crc={:#010x} [corrupt, at offset {:#X}]",
+                corrupt.chunk_type.escape_ascii(),
+                corrupt.data.len(),
+                corrupt.crc,
+                corrupt.offset
+            ),
+            None => match chunks.next() {
+                Some(chunk) => print_chunk(index, chunk, &context),
+                None => break,
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Repairs the PNG, listing every change, and writes it out.
+///
+/// An intact file is still written to an explicit output,
+/// but isn't rewritten in place.
+fn repair(args: RepairArgs) -> Result<()> {
+    let repaired = Png::repair(&read_bytes(&args.file)?)?;
+    if repaired.repairs.is_empty() {
+        println!("nothing to repair");
+    }
+    for change in &repaired.repairs {
+        println!("{change}");
+    }
+    if args.dry_run || (repaired.repairs.is_empty() && args.output.is_none()) {
+        return Ok(());
+    }
+    let output = args.output.as_deref().unwrap_or(&args.file);
+    repaired.png.save(output)
+}
+
+/// Lints every file, printing the findings as text or JSON.
+///
+/// Fails if any file has a finding at least as severe as `--fail-on`.
+/// A file that can't be read or parsed is reported as an error finding.
+fn lint(args: LintArgs) -> Result<()> {
+    let mut failed = 0;
+    let mut reports = Vec::new();
+    for file in &args.files {
+        let findings = match read_png(file) {
+            Ok(png) => png.lint(),
+            Err(error) => {
+                let message = error.to_string();
+                vec![Finding {
+                    severity: Severity::Error,
+                    code: "unreadable",
+                    message: message
+                        .strip_prefix("error: ")
+                        .unwrap_or(&message)
+                        .to_string(),
+                }]
+            }
+        };
+        if findings
+            .iter()
+            .any(|finding| finding.severity >= args.fail_on)
+        {
+            failed += 1;
+        }
+
+        match args.format {
+            LintFormat::Text => {
+                for finding in &findings {
+                    println!("{}: {finding}", file.display());
+                }
+            }
+            LintFormat::Json => {
+                let findings: Vec<String> = findings.iter().map(Finding::to_json).collect();
+                reports.push(format!(
+                    r#"{{"file":{},"findings":[{}]}}"#,
+                    json_string(&file.display().to_string()),
+                    findings.join(",")
+                ));
+            }
+        }
+    }
+    if args.format == LintFormat::Json {
+        println!("[{}]", reports.join(","));
+    }
+
+    if failed > 0 {
+        return Err(PngError::LintFailed(failed));
+    }
+    Ok(())
+}
+
+/// Lists every text entry, prints the value of one,
+/// or sets or removes one and writes the PNG back.
+fn text(args: TextArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let Some(keyword) = args.keyword else {
+        for entry in png.text_entries() {
+            println!("{entry}");
+        }
+        return Ok(());
+    };
+
+    if let Some(value) = args.set {
+        let kind = if args.lang.is_some() || args.translated.is_some() {
+            png.set_localized_text(
+                &keyword,
+                &value,
+                args.lang.as_deref().unwrap_or_default(),
+                args.translated.as_deref().unwrap_or_default(),
+            )?;
+            TextKind::International
+        } else {
+            png.set_text(&keyword, &value)?
+        };
+        png.save(&args.file)?;
+        println!("stored '{keyword}' as {kind}");
+    } else if args.remove {
+        let removed = png.remove_text(&keyword)?;
+        png.save(&args.file)?;
+        println!("removed {removed} chunk(s) with keyword '{keyword}'");
+    } else {
+        println!("{}", png.get_text(&keyword).ok_or(PngError::ChunkNotFound)?);
+    }
+    Ok(())
+}
+
+/// Lists the palette as RGBA, prints one entry, or changes
+/// one entry's color or alpha and writes the PNG back.
+fn palette(args: PaletteArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let mut palette = png.palette().ok_or(PngError::ChunkNotFound)??;
+    let transparency = png.transparency().transpose()?;
+    let colors = palette.to_rgba(transparency.as_ref());
+    let print_entry = |index: usize, [red, green, blue, alpha]: [u8; 4]| {
+        println!("#{index} {red:02x}{green:02x}{blue:02x} alpha={alpha}");
+    };
+    let Some(index) = args.index else {
+        for (index, color) in colors.into_iter().enumerate() {
+            print_entry(index, color);
+        }
+        return Ok(());
+    };
+    if index >= colors.len() {
+        return Err(Palette::invalid(format_args!(
+            "there's no entry {index} in a palette of {palette}"
+        )));
+    }
+    if args.set.is_none() && args.alpha.is_none() {
+        print_entry(index, colors[index]);
+        return Ok(());
+    }
+
+    if let Some(color) = &args.set {
+        palette.entries_mut()[index] = PaletteEntry::from_str(color)?;
+        png.set_palette(palette)?;
+        println!("set entry #{index} to {color}");
+    }
+    if let Some(alpha) = args.alpha {
+        let mut alphas: Vec<u8> = colors.iter().map(|color| color[3]).collect();
+        alphas[index] = alpha;
+        png.set_transparency(Transparency::Indexed(alphas))?;
+        println!("set the alpha of entry #{index} to {alpha}");
+    }
+    png.save(&args.file)?;
+    Ok(())
+}
+
+/// The profile name `icc --embed` uses unless given one.
+const DEFAULT_ICC_PROFILE_NAME: &str = "ICC profile";
+
+/// Prints the embedded ICC profile's header, writes the profile
+/// to a file, or embeds a new one.
+fn icc(args: IccArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    if let Some(path) = args.embed {
+        let name = args.name.as_deref().unwrap_or(DEFAULT_ICC_PROFILE_NAME);
+        let iccp = IccProfileChunk::new(name, read_bytes(&path)?)?;
+        let header = iccp.header()?;
+        let removed_srgb = png.set_icc_profile(iccp);
+        png.save(&args.file)?;
+        println!("embedded {header}");
+        if removed_srgb {
+            println!("removed the sRGB chunk, which the profile replaces");
+        }
+        return Ok(());
+    }
+
+    let iccp = png.icc_profile().ok_or(PngError::ChunkNotFound)??;
+    match args.extract {
+        Some(path) => {
+            fs::write(&path, iccp.profile())
+                .map_err(|source| PngError::FileAccess { path, source })?;
+            println!(
+                "wrote the {} bytes of '{}'",
+                iccp.profile().len(),
+                iccp.name()
+            );
+        }
+        None => println!("{iccp}"),
+    }
+    Ok(())
+}
+
+/// Lists the EXIF tags, or with `--strip`, removes the EXIF data.
+fn exif(args: ExifArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    if args.strip {
+        let removed = png.strip_exif();
+        if removed == 0 {
+            return Err(PngError::ChunkNotFound);
+        }
+        png.save(&args.file)?;
+        println!("removed {removed} eXIf chunk(s)");
+        return Ok(());
+    }
+
+    let exif = png.exif().ok_or(PngError::ChunkNotFound)??;
+    println!("{} TIFF data", exif.byte_order());
+    for entry in exif.entries()? {
+        println!("{entry}");
+    }
+    Ok(())
+}
+
+/// Prints the resolution, or with `--set`, sets it in dots per inch.
+fn dpi(args: DpiArgs) -> Result<()> {
+    let mut png = read_png(&args.file)?;
+    let Some(dpi) = args.set else {
+        let dimensions = png.physical_dimensions().ok_or(PngError::ChunkNotFound)??;
+        println!("{dimensions}");
+        return Ok(());
+    };
+
+    if !(dpi.is_finite() && dpi > 0.0) {
+        return Err(PhysicalDimensions::invalid(format_args!(
+            "DPI must be a positive number, got {dpi}"
+        )));
+    }
+    let dimensions = PhysicalDimensions::from_dpi(dpi, dpi);
+    png.set_physical_dimensions(&dimensions);
+    png.save(&args.file)?;
+    println!("set the resolution to {dimensions}");
+    Ok(())
+}
+
+/// Removes every ancillary chunk but the allowlisted ones, listing
+/// each chunk with the reason it's kept or removed in `--dry-run` mode.
+fn strip(args: StripArgs) -> Result<()> {
+    let policy = StripPolicy::new(&args.keep, &args.drop)?;
+    let mut png = read_png(&args.file)?;
+    if args.dry_run {
+        for (index, chunk) in png.chunks().iter().enumerate() {
+            let reason = policy.reason(chunk.chunk_type());
+            let action = if reason.keeps() { "keep" } else { "drop" };
+            println!("{action} #{index} {} ({reason})", chunk.chunk_type());
+        }
+        return Ok(());
+    }
+
+    let removed = png.strip(&policy);
+    for chunk in &removed {
+        println!("removed {}", chunk.chunk_type());
+    }
+    let output = args.output.as_deref().unwrap_or(&args.file);
+    png.save(output)?;
+    println!("removed {} chunk(s)", removed.len());
+    Ok(())
+}
+
+fn print_chunk(index: usize, chunk: &Chunk, context: &ImageContext) {
+    let chunk_type = chunk.chunk_type();
+    println!(
+        "#{index} {chunk_type} length={} crc={:#010x} [{}, {}, {}]",
+        chunk.length(),
+        chunk.crc(),
+        if chunk_type.is_critical() {
+            "critical"
+        } else {
+            "ancillary"
+        },
+        if chunk_type.is_public() {
+            "public"
+        } else {
+            "private"
+        },
+        if chunk_type.is_safe_to_copy() {
+            "safe to copy"
+        } else {
+            "unsafe to copy"
+        },
+    );
+    if let Some(description) = describe_chunk(chunk, context) {
+        println!("    {description}");
+    }
+}
+
+/// What `describe_chunk` needs to know about the image to decode
+/// chunks whose layout or meaning depends on it.
+#[derive(Default)]
+struct ImageContext {
+    ihdr: Option<Ihdr>,
+    palette: Option<Palette>,
+}
+
+impl ImageContext {
+    fn of(png: &Png) -> Self {
+        Self {
+            ihdr: png.ihdr().ok(),
+            palette: png.palette().and_then(|palette| palette.ok()),
+        }
+    }
+
+    /// Checks a parsed chunk against the palette, if there is one.
+    fn check_palette<T>(
+        &self,
+        chunk: Result<T>,
+        validate: impl FnOnce(&T, &Palette) -> Result<()>,
+    ) -> Result<T> {
+        let chunk = chunk?;
+        if let Some(palette) = &self.palette {
+            validate(&chunk, palette)?;
+        }
+        Ok(chunk)
+    }
+}
+
+/// Decodes the data of the chunk types we know the layout of.
+///
+/// Chunks whose layout depends on the image header can only be
+/// decoded if the image has a valid one.
+fn describe_chunk(chunk: &Chunk, context: &ImageContext) -> Option<String> {
+    let ihdr = context.ihdr.as_ref().ok_or(PngError::IhdrNotFirst);
+    let description = match chunk.chunk_type().bytes() {
+        Ihdr::CHUNK_TYPE => Ihdr::from_chunk(chunk).map(|ihdr| ihdr.to_string()),
+        TextChunk::CHUNK_TYPE => TextChunk::from_chunk(chunk).map(|text| text.to_string()),
+        ZTxtChunk::CHUNK_TYPE => ZTxtChunk::from_chunk(chunk).map(|ztxt| ztxt.to_string()),
+        ITxtChunk::CHUNK_TYPE => ITxtChunk::from_chunk(chunk).map(|itxt| itxt.to_string()),
+        TimeChunk::CHUNK_TYPE => TimeChunk::from_chunk(chunk).map(|time| time.to_string()),
+        PhysicalDimensions::CHUNK_TYPE => {
+            PhysicalDimensions::from_chunk(chunk).map(|phys| phys.to_string())
+        }
+        Gamma::CHUNK_TYPE => Gamma::from_chunk(chunk).map(|gamma| gamma.to_string()),
+        Chromaticities::CHUNK_TYPE => {
+            Chromaticities::from_chunk(chunk).map(|chrm| chrm.to_string())
+        }
+        Srgb::CHUNK_TYPE => Srgb::from_chunk(chunk).map(|srgb| srgb.to_string()),
+        Palette::CHUNK_TYPE => Palette::from_chunk(chunk).and_then(|palette| {
+            if let Ok(ihdr) = ihdr {
+                palette.validate(ihdr)?;
+            }
+            Ok(palette.to_string())
+        }),
+        Transparency::CHUNK_TYPE => context
+            .check_palette(
+                ihdr.and_then(|ihdr| Transparency::from_chunk(chunk, ihdr)),
+                Transparency::validate_palette,
+            )
+            .map(|trns| trns.to_string()),
+        Background::CHUNK_TYPE => context
+            .check_palette(
+                ihdr.and_then(|ihdr| Background::from_chunk(chunk, ihdr)),
+                Background::validate_palette,
+            )
+            .map(|bkgd| bkgd.to_string()),
+        Histogram::CHUNK_TYPE => context
+            .check_palette(Histogram::from_chunk(chunk), Histogram::validate_palette)
+            .map(|hist| hist.to_string()),
+        SignificantBits::CHUNK_TYPE => ihdr
+            .and_then(|ihdr| SignificantBits::from_chunk(chunk, ihdr))
+            .map(|sbit| sbit.to_string()),
+        ExifChunk::CHUNK_TYPE => ExifChunk::from_chunk(chunk).map(|exif| exif.to_string()),
+        IccProfileChunk::CHUNK_TYPE => {
+            IccProfileChunk::from_chunk(chunk).map(|iccp| iccp.to_string())
+        }
+        SuggestedPalette::CHUNK_TYPE => {
+            SuggestedPalette::from_chunk(chunk).map(|splt| splt.to_string())
+        }
+        _ => return None,
+    };
+    Some(description.unwrap_or_else(|error| error.to_string()))
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|source| PngError::FileAccess {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn read_png(path: &Path) -> Result<Png> {
+    Png::try_from(read_bytes(path)?.as_ref())
+}
+
+/// Parses the PNG at `path` with `Png::parse_lenient`,
+/// reporting every problem found on stderr.
+fn read_png_lenient(path: &Path, policy: CorruptChunkPolicy) -> Result<RecoveredPng> {
+    let recovered = Png::parse_lenient(&read_bytes(path)?, policy)?;
+    for diagnostic in &recovered.diagnostics {
+        eprintln!("{diagnostic}");
+    }
+    Ok(recovered)
+}
+
+#[cfg(test)]
+mod commands_tests {
+    use super::*;
+    use crate::ihdr::ColorType;
+    use crate::test_utils::{chunk, chunk_with_data, ihdr};
+    use std::path::PathBuf;
+
+    fn temp_png(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "hushpong-commands-{}-{name}.png",
+            std::process::id()
+        ));
+        let png = Png::from_chunks(vec![ihdr(ColorType::Rgb, 8).to_chunk(), chunk("IEND")]);
+        png.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_encode_then_remove() {
+        let path = temp_png("encode-remove");
+        encode(EncodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            message: "hidden".to_string(),
+            output: None,
+            touch: false,
+        })
+        .unwrap();
+
+        let png = read_png(&path).unwrap();
+        let (_, chunk) = png.search_chunk("ruSt").unwrap();
+        assert_eq!(chunk.data_as_string().unwrap(), "hidden");
+
+        assert!(png.find::<TimeChunk>().is_none());
+
+        remove(RemoveArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            all: false,
+            touch: true,
+        })
+        .unwrap();
+        let png = read_png(&path).unwrap();
+        assert!(png.search_chunk("ruSt").is_none());
+        assert!(png.find::<TimeChunk>().unwrap().is_ok());
+
+        remove(RemoveArgs {
+            file: path.clone(),
+            chunk_type: "tIME".to_string(),
+            all: true,
+            touch: false,
+        })
+        .unwrap();
+        assert!(read_png(&path).unwrap().find::<TimeChunk>().is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_decode_missing_chunk() {
+        let path = temp_png("decode-missing");
+        let result = decode(DecodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            lenient: false,
+        });
+        assert!(matches!(result, Err(PngError::ChunkNotFound)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_lenient_decode_skips_corrupt_chunk() {
+        let path = temp_png("decode-lenient");
+        let mut png = read_png(&path).unwrap();
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"hidden".to_vec(),
+        ));
+        let mut bytes = png.as_bytes();
+        // Corrupt the CRC of the IEND chunk in front of the message,
+        // which comes after the 25-byte IHDR chunk.
+        bytes[8 + 25 + 11] ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+
+        let strict = decode(DecodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            lenient: false,
+        });
+        assert!(strict.is_err());
+        let lenient = decode(DecodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            lenient: true,
+        });
+        assert!(lenient.is_ok());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_repair_fixes_crc() {
+        let path = temp_png("repair");
+        let expected = fs::read(&path).unwrap();
+        let mut bytes = expected.clone();
+        *bytes.last_mut().unwrap() ^= 0xFF;
+        fs::write(&path, bytes).unwrap();
+
+        let repair_args = |dry_run| RepairArgs {
+            file: path.clone(),
+            output: None,
+            dry_run,
+        };
+        repair(repair_args(true)).unwrap();
+        assert!(read_png(&path).is_err());
+        repair(repair_args(false)).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), expected);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encode_repair_decode() {
+        let path = temp_png("encode-repair-decode");
+        encode(EncodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            message: "hidden".to_string(),
+            output: None,
+            touch: false,
+        })
+        .unwrap();
+        repair(RepairArgs {
+            file: path.clone(),
+            output: None,
+            dry_run: false,
+        })
+        .unwrap();
+        decode(DecodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            lenient: false,
+        })
+        .unwrap();
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_repair_intact_file_to_output() {
+        let path = temp_png("repair-intact");
+        let output = temp_png("repair-intact-output");
+        fs::remove_file(&output).unwrap();
+
+        repair(RepairArgs {
+            file: path.clone(),
+            output: Some(output.clone()),
+            dry_run: false,
+        })
+        .unwrap();
+        assert_eq!(fs::read(&output).unwrap(), fs::read(&path).unwrap());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_encode_rejects_reserved_bit() {
+        let path = temp_png("encode-reserved");
+        let result = encode(EncodeArgs {
+            file: path.clone(),
+            chunk_type: "rust".to_string(),
+            message: "hidden".to_string(),
+            output: None,
+            touch: false,
+        });
+        assert!(matches!(result, Err(PngError::InvalidReservedBit(_))));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_encode_rejects_non_image() {
+        let path = temp_png("encode-non-image");
+        Png::from_chunks(vec![chunk("IEND")]).save(&path).unwrap();
+        let result = encode(EncodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            message: "hidden".to_string(),
+            output: None,
+            touch: false,
+        });
+        assert!(matches!(result, Err(PngError::IhdrNotFirst)));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_describe_chunk() {
+        let description = describe_chunk(
+            &ihdr(ColorType::Rgb, 8).to_chunk(),
+            &ImageContext::default(),
+        );
+        assert_eq!(description.as_deref(), Some("1x1, 8-bit RGB"));
+        assert!(describe_chunk(&chunk("IEND"), &ImageContext::default()).is_none());
+
+        let text = Chunk::new(
+            ChunkType::from_str("tEXt").unwrap(),
+            b"Title\0caf\xE9".to_vec(),
+        );
+        assert_eq!(
+            describe_chunk(&text, &ImageContext::default()).as_deref(),
+            Some("Title: café")
+        );
+
+        let gamma = Chunk::new(ChunkType::from_str("gAMA").unwrap(), vec![0, 0]);
+        assert_eq!(
+            describe_chunk(&gamma, &ImageContext::default()).as_deref(),
+            Some("error: invalid gAMA chunk: length must be 4, got 2")
+        );
+
+        let trns = Chunk::new(ChunkType::from_str("tRNS").unwrap(), vec![0, 1, 0, 2, 0, 3]);
+        assert_eq!(
+            describe_chunk(&trns, &ImageContext::default()).as_deref(),
+            Some("error: the first chunk must be IHDR")
+        );
+        let context = ImageContext {
+            ihdr: Some(ihdr(ColorType::Rgb, 8)),
+            palette: None,
+        };
+        assert_eq!(
+            describe_chunk(&trns, &context).as_deref(),
+            Some("transparent color (1, 2, 3)")
+        );
+    }
+
+    #[test]
+    fn test_describe_chunk_against_palette() {
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Rgb, 8).to_chunk(),
+            Chunk::new(ChunkType::from_str("PLTE").unwrap(), vec![0; 6]),
+        ]);
+        let context = ImageContext::of(&png);
+        let hist = Chunk::new(ChunkType::from_str("hIST").unwrap(), vec![0, 1, 0, 2]);
+        assert_eq!(
+            describe_chunk(&hist, &context).as_deref(),
+            Some("frequencies for 2 entries, entry 1 is the most used")
+        );
+        let hist = Chunk::new(ChunkType::from_str("hIST").unwrap(), vec![0, 1]);
+        assert_eq!(
+            describe_chunk(&hist, &context).as_deref(),
+            Some("error: invalid hIST chunk: 1 frequencies for 2 palette entries")
+        );
+        let sbit = Chunk::new(ChunkType::from_str("sBIT").unwrap(), vec![5, 6, 5]);
+        assert_eq!(
+            describe_chunk(&sbit, &context).as_deref(),
+            Some("significant bits: red 5, green 6, blue 5")
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        let path = temp_png("lint");
+        let idat = Chunk::new(ChunkType::from_str("IDAT").unwrap(), Vec::new());
+        let mut png = Png::from_chunks(vec![
+            ihdr(ColorType::Rgb, 8).to_chunk(),
+            idat,
+            chunk("IEND"),
+        ]);
+        png.save(&path).unwrap();
+        let lint_args = |fail_on| LintArgs {
+            files: vec![path.clone()],
+            format: LintFormat::Json,
+            fail_on,
+        };
+        assert!(lint(lint_args(Severity::Info)).is_ok());
+
+        encode(EncodeArgs {
+            file: path.clone(),
+            chunk_type: "ruSt".to_string(),
+            message: "hidden".to_string(),
+            output: None,
+            touch: false,
+        })
+        .unwrap();
+        // An encoded message is only a warning, for being a private chunk.
+        assert!(lint(lint_args(Severity::Error)).is_ok());
+        assert!(matches!(
+            lint(lint_args(Severity::Warning)),
+            Err(PngError::LintFailed(1))
+        ));
+
+        png.append_chunk(Chunk::new(
+            ChunkType::from_str("ruSt").unwrap(),
+            b"hidden".to_vec(),
+        ));
+        png.save(&path).unwrap();
+        // A chunk after IEND is an error.
+        assert!(matches!(
+            lint(lint_args(Severity::Error)),
+            Err(PngError::LintFailed(1))
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_lint_reports_unreadable_files() {
+        let result = lint(LintArgs {
+            files: vec![PathBuf::from("/nonexistent/hushpong.png")],
+            format: LintFormat::Text,
+            fail_on: Severity::Error,
+        });
+        assert!(matches!(result, Err(PngError::LintFailed(1))));
+    }
+
+    #[test]
+    fn test_text() {
+        let path = temp_png("text");
+        let text_args = |keyword: Option<&str>, set: Option<&str>, remove| TextArgs {
+            file: path.clone(),
+            keyword: keyword.map(str::to_string),
+            set: set.map(str::to_string),
+            lang: None,
+            translated: None,
+            remove,
+        };
+        text(text_args(Some("Title"), Some("Grüße"), false)).unwrap();
+        assert_eq!(
+            read_png(&path).unwrap().get_text("Title").as_deref(),
+            Some("Grüße")
+        );
+        text(text_args(None, None, false)).unwrap();
+        text(text_args(Some("Title"), None, false)).unwrap();
+
+        text(TextArgs {
+            lang: Some("de".to_string()),
+            translated: Some("Titel".to_string()),
+            ..text_args(Some("Title"), Some("Grüße"), false)
+        })
+        .unwrap();
+        let entries = read_png(&path).unwrap().text_entries();
+        assert_eq!(entries[0].to_string(), "Title (iTXt, de, Titel): Grüße");
+
+        text(text_args(Some("Title"), None, true)).unwrap();
+        assert!(matches!(
+            text(text_args(Some("Title"), None, false)),
+            Err(PngError::ChunkNotFound)
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_palette() {
+        let path = temp_png("palette");
+        let palette_args = |index, set: Option<&str>, alpha| PaletteArgs {
+            file: path.clone(),
+            index,
+            set: set.map(str::to_string),
+            alpha,
+        };
+        assert!(matches!(
+            palette(palette_args(None, None, None)),
+            Err(PngError::ChunkNotFound)
+        ));
+
+        let mut png = Png::from_chunks(vec![
+            ihdr(ColorType::Indexed, 1).to_chunk(),
+            chunk_with_data("PLTE", &[0; 6]),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ]);
+        png.save(&path).unwrap();
+        palette(palette_args(None, None, None)).unwrap();
+        palette(palette_args(Some(1), Some("ff8000"), Some(128))).unwrap();
+        png = read_png(&path).unwrap();
+        let transparency = png.transparency().transpose().unwrap();
+        assert_eq!(
+            png.palette()
+                .unwrap()
+                .unwrap()
+                .to_rgba(transparency.as_ref()),
+            [[0, 0, 0, 255], [255, 128, 0, 128]]
+        );
+        palette(palette_args(Some(1), None, None)).unwrap();
+
+        assert!(palette(palette_args(Some(2), None, None)).is_err());
+        assert!(palette(palette_args(Some(0), Some("orange"), None)).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dpi() {
+        let path = temp_png("dpi");
+        let dpi_args = |set| DpiArgs {
+            file: path.clone(),
+            set,
+        };
+        assert!(matches!(dpi(dpi_args(None)), Err(PngError::ChunkNotFound)));
+
+        dpi(dpi_args(Some(300.0))).unwrap();
+        dpi(dpi_args(Some(72.0))).unwrap();
+        let png = read_png(&path).unwrap();
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "pHYs");
+        assert_eq!(png.chunks().len(), 3);
+        let (x, y) = png.physical_dimensions().unwrap().unwrap().dpi().unwrap();
+        assert!((x - 72.0).abs() < 0.1 && (y - 72.0).abs() < 0.1);
+        dpi(dpi_args(None)).unwrap();
+
+        assert!(dpi(dpi_args(Some(0.0))).is_err());
+        assert!(dpi(dpi_args(Some(f64::NAN))).is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_icc() {
+        let path = temp_png("icc");
+        let profile_path = path.with_extension("icc");
+        let mut profile = vec![0; 128];
+        profile[..4].copy_from_slice(&128u32.to_be_bytes());
+        profile[36..40].copy_from_slice(b"acsp");
+        fs::write(&profile_path, &profile).unwrap();
+        let icc_args = |extract: Option<&Path>, embed: Option<&Path>| IccArgs {
+            file: path.clone(),
+            extract: extract.map(Path::to_path_buf),
+            embed: embed.map(Path::to_path_buf),
+            name: None,
+        };
+        assert!(matches!(
+            icc(icc_args(None, None)),
+            Err(PngError::ChunkNotFound)
+        ));
+
+        icc(icc_args(None, Some(&profile_path))).unwrap();
+        let iccp = read_png(&path).unwrap().icc_profile().unwrap().unwrap();
+        assert_eq!(iccp.name(), DEFAULT_ICC_PROFILE_NAME);
+        icc(icc_args(None, None)).unwrap();
+
+        fs::remove_file(&profile_path).unwrap();
+        icc(icc_args(Some(&profile_path), None)).unwrap();
+        assert_eq!(fs::read(&profile_path).unwrap(), profile);
+
+        // Anything without an ICC header is refused.
+        fs::write(&profile_path, b"not a profile").unwrap();
+        assert!(icc(icc_args(None, Some(&profile_path))).is_err());
+
+        fs::remove_file(profile_path).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_exif() {
+        let path = temp_png("exif");
+        let exif_args = |strip| ExifArgs {
+            file: path.clone(),
+            strip,
+        };
+        assert!(matches!(
+            exif(exif_args(false)),
+            Err(PngError::ChunkNotFound)
+        ));
+
+        let mut png = read_png(&path).unwrap();
+        let tiff = b"MM\0*\0\0\0\x08\0\0\0\0\0\0";
+        png.insert_before_iend(Chunk::new(
+            ChunkType::from_str("eXIf").unwrap(),
+            tiff.to_vec(),
+        ));
+        png.save(&path).unwrap();
+        exif(exif_args(false)).unwrap();
+
+        exif(exif_args(true)).unwrap();
+        assert!(read_png(&path).unwrap().exif().is_none());
+        assert!(matches!(
+            exif(exif_args(true)),
+            Err(PngError::ChunkNotFound)
+        ));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_strip() {
+        let path = temp_png("strip");
+        let mut png = read_png(&path).unwrap();
+        png.set_text("Author", "someone").unwrap();
+        png.touch().unwrap();
+        png.save(&path).unwrap();
+        let strip_args = |keep: &[&str], dry_run| StripArgs {
+            file: path.clone(),
+            output: None,
+            keep: keep.iter().map(|t| t.to_string()).collect(),
+            drop: Vec::new(),
+            dry_run,
+        };
+
+        strip(strip_args(&[], true)).unwrap();
+        assert_eq!(read_png(&path).unwrap().chunks().len(), 4);
+
+        strip(strip_args(&["tEXt"], false)).unwrap();
+        let png = read_png(&path).unwrap();
+        assert_eq!(png.get_text("Author").as_deref(), Some("someone"));
+        assert!(png.find::<TimeChunk>().is_none());
+
+        strip(strip_args(&[], false)).unwrap();
+        assert_eq!(read_png(&path).unwrap().chunks().len(), 2);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_missing_file() {
+        let result = read_png(Path::new("/nonexistent/hushpong.png"));
+        assert!(matches!(result, Err(PngError::FileAccess { .. })));
+    }
+}
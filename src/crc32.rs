@@ -2,40 +2,470 @@ use crate::chunk_type::ChunkType;
 
 const POLY: u32 = 0xEDB88320;
 const SIZE: usize = 256;
+/// The value of the CRC register before any bytes have been fed in.
+pub(crate) const CRC_INIT: u32 = 0xFFFFFFFF;
 
-fn init_crc() -> [u32; SIZE] {
+/// The CRC lookup tables, built once at compile time.
+///
+/// `CRC_TABLES[0]` is the classic byte-at-a-time table. `CRC_TABLES[k]`
+/// gives the effect of a byte followed by `k` zero bytes, which lets the
+/// slice-by-N loops below look up N bytes independently of each other.
+static CRC_TABLES: [[u32; SIZE]; 16] = init_crc_tables();
+
+const fn init_crc() -> [u32; SIZE] {
     let mut crc_table: [u32; SIZE] = [0; SIZE];
-    let mut c: u32;
+    let mut byte = 0;
 
-    for (byte, crc) in crc_table.iter_mut().enumerate() {
-        c = byte as u32;
-        for _ in 0..8 {
+    while byte < SIZE {
+        let mut c = byte as u32;
+        let mut bit = 0;
+        while bit < 8 {
             if c & 1 == 1 {
                 c = POLY ^ (c >> 1);
             } else {
                 c >>= 1;
             }
+            bit += 1;
         }
-        *crc = c;
+        crc_table[byte] = c;
+        byte += 1;
     }
     crc_table
 }
 
-fn update_crc(buf: &[u8], crc_table: &[u32; 256]) -> u32 {
-    let mut crc: u32 = 0xFFFFFFFF;
+const fn init_crc_tables() -> [[u32; SIZE]; 16] {
+    let mut crc_tables = [[0; SIZE]; 16];
+    crc_tables[0] = init_crc();
 
+    let mut table = 1;
+    while table < 16 {
+        let mut byte = 0;
+        while byte < SIZE {
+            let previous = crc_tables[table - 1][byte];
+            crc_tables[table][byte] = (previous >> 8) ^ crc_tables[0][(previous & 0xFF) as usize];
+            byte += 1;
+        }
+        table += 1;
+    }
+    crc_tables
+}
+
+/// Feeds `buf` into a running CRC register that
+/// started out as `CRC_INIT`.
+///
+/// Uses carry-less multiplication when the CPU supports it,
+/// and slice-by-16 otherwise.
+pub(crate) fn update_crc(crc: u32, buf: &[u8]) -> u32 {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if buf.len() >= pclmulqdq::MIN_LENGTH && pclmulqdq::is_supported() {
+        // SAFETY: the required CPU features were just detected.
+        return unsafe { pclmulqdq::update_crc(crc, buf) };
+    }
+    update_crc_slice_by_16(crc, buf)
+}
+
+/// Feeds `buf` into the CRC register one byte at a time.
+pub(crate) fn update_crc_bytewise(mut crc: u32, buf: &[u8]) -> u32 {
     for byte in buf {
         let index = (crc ^ (*byte as u32)) & 0xFF;
-        crc = crc_table[index as usize] ^ (crc >> 8);
+        crc = CRC_TABLES[0][index as usize] ^ (crc >> 8);
     }
 
     crc
 }
 
+/// Feeds `buf` into the CRC register eight bytes at a time.
+pub(crate) fn update_crc_slice_by_8(mut crc: u32, buf: &[u8]) -> u32 {
+    let t = &CRC_TABLES;
+    let mut blocks = buf.chunks_exact(8);
+
+    for block in &mut blocks {
+        let one = u32::from_le_bytes([block[0], block[1], block[2], block[3]]) ^ crc;
+        let two = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+        crc = t[7][(one & 0xFF) as usize]
+            ^ t[6][((one >> 8) & 0xFF) as usize]
+            ^ t[5][((one >> 16) & 0xFF) as usize]
+            ^ t[4][(one >> 24) as usize]
+            ^ t[3][(two & 0xFF) as usize]
+            ^ t[2][((two >> 8) & 0xFF) as usize]
+            ^ t[1][((two >> 16) & 0xFF) as usize]
+            ^ t[0][(two >> 24) as usize];
+    }
+
+    update_crc_bytewise(crc, blocks.remainder())
+}
+
+/// Feeds `buf` into the CRC register sixteen bytes at a time.
+pub(crate) fn update_crc_slice_by_16(mut crc: u32, buf: &[u8]) -> u32 {
+    let t = &CRC_TABLES;
+    let mut blocks = buf.chunks_exact(16);
+
+    for block in &mut blocks {
+        let word =
+            |i: usize| u32::from_le_bytes([block[i], block[i + 1], block[i + 2], block[i + 3]]);
+        let one = word(0) ^ crc;
+        let two = word(4);
+        let three = word(8);
+        let four = word(12);
+        crc = t[15][(one & 0xFF) as usize]
+            ^ t[14][((one >> 8) & 0xFF) as usize]
+            ^ t[13][((one >> 16) & 0xFF) as usize]
+            ^ t[12][(one >> 24) as usize]
+            ^ t[11][(two & 0xFF) as usize]
+            ^ t[10][((two >> 8) & 0xFF) as usize]
+            ^ t[9][((two >> 16) & 0xFF) as usize]
+            ^ t[8][(two >> 24) as usize]
+            ^ t[7][(three & 0xFF) as usize]
+            ^ t[6][((three >> 8) & 0xFF) as usize]
+            ^ t[5][((three >> 16) & 0xFF) as usize]
+            ^ t[4][(three >> 24) as usize]
+            ^ t[3][(four & 0xFF) as usize]
+            ^ t[2][((four >> 8) & 0xFF) as usize]
+            ^ t[1][((four >> 16) & 0xFF) as usize]
+            ^ t[0][(four >> 24) as usize];
+    }
+
+    update_crc_slice_by_8(crc, blocks.remainder())
+}
+
+/// CRC-32 by folding with carry-less multiplication, as described in
+/// Intel's "Fast CRC Computation for Generic Polynomials Using PCLMULQDQ
+/// Instruction". Only used on CPUs where `is_supported` holds.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) mod pclmulqdq {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86 as arch;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64 as arch;
+
+    use arch::__m128i;
+
+    /// Below this many bytes the table-driven code is faster.
+    pub(crate) const MIN_LENGTH: usize = 128;
+
+    // Folding constants for the bit-reflected polynomial 0xEDB88320.
+    const K1: i64 = 0x154442bd4;
+    const K2: i64 = 0x1c6e41596;
+    const K3: i64 = 0x1751997d0;
+    const K4: i64 = 0x0ccaa009e;
+    const K5: i64 = 0x163cd6124;
+    // The polynomial and its Barrett reduction constant.
+    const P_X: i64 = 0x1DB710641;
+    const U_PRIME: i64 = 0x1F7011641;
+
+    /// Checks for the CPU features `update_crc` needs.
+    pub(crate) fn is_supported() -> bool {
+        is_x86_feature_detected!("pclmulqdq")
+            && is_x86_feature_detected!("sse2")
+            && is_x86_feature_detected!("sse4.1")
+    }
+
+    /// Feeds `buf` into the CRC register 64 bytes at a time.
+    ///
+    /// # Safety
+    ///
+    /// The CPU must support `pclmulqdq`, `sse2` and `sse4.1`.
+    #[target_feature(enable = "pclmulqdq", enable = "sse2", enable = "sse4.1")]
+    pub(crate) unsafe fn update_crc(crc: u32, mut buf: &[u8]) -> u32 {
+        if buf.len() < MIN_LENGTH {
+            return super::update_crc_slice_by_16(crc, buf);
+        }
+
+        // Fold 64 bytes at a time into four 128-bit lanes.
+        let mut x3 = load(&mut buf);
+        let mut x2 = load(&mut buf);
+        let mut x1 = load(&mut buf);
+        let mut x0 = load(&mut buf);
+        x3 = arch::_mm_xor_si128(x3, arch::_mm_cvtsi32_si128(crc as i32));
+
+        let k1k2 = arch::_mm_set_epi64x(K2, K1);
+        while buf.len() >= 64 {
+            x3 = fold(x3, load(&mut buf), k1k2);
+            x2 = fold(x2, load(&mut buf), k1k2);
+            x1 = fold(x1, load(&mut buf), k1k2);
+            x0 = fold(x0, load(&mut buf), k1k2);
+        }
+
+        // Fold the four lanes into one, then the rest 16 bytes at a time.
+        let k3k4 = arch::_mm_set_epi64x(K4, K3);
+        let mut x = fold(x3, x2, k3k4);
+        x = fold(x, x1, k3k4);
+        x = fold(x, x0, k3k4);
+        while buf.len() >= 16 {
+            x = fold(x, load(&mut buf), k3k4);
+        }
+
+        // Reduce 128 bits to 64.
+        let low_32 = arch::_mm_set_epi32(0, 0, 0, !0);
+        let x = arch::_mm_xor_si128(
+            arch::_mm_clmulepi64_si128(x, k3k4, 0x10),
+            arch::_mm_srli_si128(x, 8),
+        );
+        let x = arch::_mm_xor_si128(
+            arch::_mm_clmulepi64_si128(
+                arch::_mm_and_si128(x, low_32),
+                arch::_mm_set_epi64x(0, K5),
+                0x00,
+            ),
+            arch::_mm_srli_si128(x, 4),
+        );
+
+        // Barrett reduction from 64 bits to the 32-bit register.
+        let pu = arch::_mm_set_epi64x(U_PRIME, P_X);
+        let t1 = arch::_mm_clmulepi64_si128(arch::_mm_and_si128(x, low_32), pu, 0x10);
+        let t2 = arch::_mm_clmulepi64_si128(arch::_mm_and_si128(t1, low_32), pu, 0x00);
+        let crc = arch::_mm_extract_epi32(arch::_mm_xor_si128(x, t2), 1) as u32;
+
+        super::update_crc_slice_by_16(crc, buf)
+    }
+
+    #[target_feature(enable = "pclmulqdq", enable = "sse2")]
+    unsafe fn fold(a: __m128i, b: __m128i, keys: __m128i) -> __m128i {
+        let t1 = arch::_mm_clmulepi64_si128(a, keys, 0x00);
+        let t2 = arch::_mm_clmulepi64_si128(a, keys, 0x11);
+        arch::_mm_xor_si128(arch::_mm_xor_si128(b, t1), t2)
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn load(buf: &mut &[u8]) -> __m128i {
+        debug_assert!(buf.len() >= 16);
+        let value = arch::_mm_loadu_si128(buf.as_ptr() as *const __m128i);
+        *buf = &buf[16..];
+        value
+    }
+}
+
+/// Calculates the CRC of a chunk from its chunk type and data.
 pub(crate) fn calculate_crc(ctype: &ChunkType, data: &[u8]) -> u32 {
-    let crc_table = init_crc();
+    let mut hasher = Crc32::new();
+    hasher.update(&ctype.bytes());
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// An incremental CRC-32 hasher, for data that arrives in pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Crc32 {
+    /// The CRC register, before the final inversion.
+    state: u32,
+}
+
+impl Crc32 {
+    /// Creates a hasher that hasn't seen any bytes yet.
+    pub(crate) fn new() -> Self {
+        Self { state: CRC_INIT }
+    }
+
+    /// Feeds the next piece of data into the hasher.
+    pub(crate) fn update(&mut self, buf: &[u8]) {
+        self.state = update_crc(self.state, buf);
+    }
+
+    /// Returns the CRC of everything fed in so far.
+    ///
+    /// The hasher can keep being updated afterwards.
+    pub(crate) fn finalize(&self) -> u32 {
+        self.state ^ 0xFFFFFFFFu32
+    }
+
+    /// Returns the CRC of `a` followed by `b`, given only the CRC of `a`,
+    /// the CRC of `b` and the length of `b`.
+    ///
+    /// This is the same as zlib's `crc32_combine`: appending `len_b` bytes
+    /// multiplies `crc_a` by x^(8 * len_b) modulo the CRC polynomial.
+    #[allow(
+        dead_code,
+        reason = "part of the CRC API, for checksumming chunks written in parts"
+    )]
+    pub(crate) fn combine(crc_a: u32, crc_b: u32, len_b: u64) -> u32 {
+        multiply_mod_poly(x_pow_8n_mod_poly(len_b), crc_a) ^ crc_b
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::hash::Hasher for Crc32 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        u64::from(self.finalize())
+    }
+}
+
+/// x^(2^k) modulo the CRC polynomial, for `k` in `0..32`.
+///
+/// Like the CRC register, polynomials here are bit-reflected:
+/// x^0 is the top bit and x^31 the bottom one.
+static X_POW_2K_TABLE: [u32; 32] = init_x_pow_2k_table();
+
+const fn init_x_pow_2k_table() -> [u32; 32] {
+    let mut table = [0; 32];
+    // x^1
+    table[0] = 1 << 30;
+    let mut k = 1;
+    while k < 32 {
+        table[k] = multiply_mod_poly(table[k - 1], table[k - 1]);
+        k += 1;
+    }
+    table
+}
 
-    let crc = update_crc(&[ctype.bytes().as_slice(), data].concat(), &crc_table);
+/// Multiplies two bit-reflected polynomials modulo the CRC polynomial.
+const fn multiply_mod_poly(a: u32, mut b: u32) -> u32 {
+    let mut product = 0;
+    let mut mask = 1 << 31;
+    while mask != 0 {
+        if a & mask != 0 {
+            product ^= b;
+        }
+        mask >>= 1;
+        b = if b & 1 == 1 { (b >> 1) ^ POLY } else { b >> 1 };
+    }
+    product
+}
+
+/// x^(8 * n) modulo the CRC polynomial, the effect of appending `n` bytes.
+fn x_pow_8n_mod_poly(mut n: u64) -> u32 {
+    // x^0
+    let mut power = 1 << 31;
+    // Appending one byte multiplies by x^8 = x^(2^3).
+    let mut k = 3;
+    while n != 0 {
+        if n & 1 == 1 {
+            power = multiply_mod_poly(X_POW_2K_TABLE[k % 32], power);
+        }
+        n >>= 1;
+        k += 1;
+    }
+    power
+}
+
+#[cfg(test)]
+mod crc32_tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_crc_table() {
+        assert_eq!(CRC_TABLES[0][0], 0);
+        assert_eq!(CRC_TABLES[0][1], 0x77073096);
+        assert_eq!(CRC_TABLES[0][255], 0x2D02EF8D);
+    }
+
+    /// Deterministic, patternless test data.
+    fn test_data(length: usize) -> Vec<u8> {
+        let mut state: u32 = 0x12345678;
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
 
-    crc ^ 0xFFFFFFFFu32
+    /// Checks `update` against the byte-at-a-time loop for every length up
+    /// to a few blocks, at every alignment and from several starting CRCs.
+    fn assert_matches_bytewise(update: impl Fn(u32, &[u8]) -> u32) {
+        let data = test_data(1024 + 16);
+        for start in 0..16 {
+            for length in (0..=600).chain([1023, 1024]) {
+                let buf = &data[start..start + length];
+                for crc in [CRC_INIT, 0, 0x9E3779B9] {
+                    assert_eq!(
+                        update(crc, buf),
+                        update_crc_bytewise(crc, buf),
+                        "start {start}, length {length}, crc {crc:#x}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_slice_by_8_matches_bytewise() {
+        assert_matches_bytewise(update_crc_slice_by_8);
+    }
+
+    #[test]
+    fn test_slice_by_16_matches_bytewise() {
+        assert_matches_bytewise(update_crc_slice_by_16);
+    }
+
+    #[test]
+    fn test_update_crc_matches_bytewise() {
+        assert_matches_bytewise(update_crc);
+    }
+
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    #[test]
+    fn test_pclmulqdq_matches_bytewise() {
+        if !pclmulqdq::is_supported() {
+            return;
+        }
+        // SAFETY: the required CPU features were just detected.
+        assert_matches_bytewise(|crc, buf| unsafe { pclmulqdq::update_crc(crc, buf) });
+    }
+
+    #[test]
+    fn test_calculate_crc_of_empty_chunk() {
+        let chunk_type = ChunkType::from_str("IEND").unwrap();
+        assert_eq!(calculate_crc(&chunk_type, &[]), 0xAE426082);
+    }
+
+    #[test]
+    fn test_calculate_crc_matches_running_crc() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "My life is like an eternal night...".as_bytes();
+
+        let mut hasher = Crc32::new();
+        hasher.update(&chunk_type.bytes());
+        data.chunks(5).for_each(|piece| hasher.update(piece));
+        assert_eq!(calculate_crc(&chunk_type, data), hasher.finalize());
+        assert_eq!(calculate_crc(&chunk_type, data), 2591807180);
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        let mut hasher = Crc32::new();
+        assert_eq!(hasher.finalize(), 0);
+        hasher.update(b"123456789");
+        assert_eq!(hasher.finalize(), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_crc32_hasher_trait() {
+        use std::hash::Hasher;
+
+        let mut hasher = Crc32::default();
+        hasher.write(b"1234");
+        hasher.write(b"56789");
+        assert_eq!(hasher.finish(), 0xCBF43926);
+    }
+
+    #[test]
+    fn test_crc32_combine() {
+        let data = test_data(3000);
+        let crc_of = |buf: &[u8]| {
+            let mut hasher = Crc32::new();
+            hasher.update(buf);
+            hasher.finalize()
+        };
+        for split in [0, 1, 7, 100, 1500, 2999, 3000] {
+            let (a, b) = data.split_at(split);
+            assert_eq!(
+                Crc32::combine(crc_of(a), crc_of(b), b.len() as u64),
+                crc_of(&data),
+                "split at {split}"
+            );
+        }
+    }
 }
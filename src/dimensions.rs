@@ -0,0 +1,154 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::PngError,
+    png::Png,
+    typed_chunk::{be_u32, TypedChunk},
+};
+
+/// Meters per inch, for converting pixels per meter to DPI.
+const METERS_PER_INCH: f64 = 0.0254;
+
+/// What the pixels per unit of `PhysicalDimensions` are measured in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Unit {
+    /// Only the aspect ratio of the pixels is known.
+    Unknown = 0,
+    Meter = 1,
+}
+
+/// The intended pixel size or aspect ratio (`pHYs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PhysicalDimensions {
+    pub(crate) pixels_per_unit_x: u32,
+    pub(crate) pixels_per_unit_y: u32,
+    pub(crate) unit: Unit,
+}
+
+impl PhysicalDimensions {
+    /// The length of the pHYs chunk data.
+    pub(crate) const LENGTH: usize = 9;
+
+    /// Returns the horizontal and vertical resolution in dots per inch,
+    /// if the unit is known.
+    pub(crate) fn dpi(&self) -> Option<(f64, f64)> {
+        (self.unit == Unit::Meter).then(|| {
+            (
+                f64::from(self.pixels_per_unit_x) * METERS_PER_INCH,
+                f64::from(self.pixels_per_unit_y) * METERS_PER_INCH,
+            )
+        })
+    }
+
+    /// Creates a pHYs for the given resolution in dots per inch.
+    pub(crate) fn from_dpi(x: f64, y: f64) -> Self {
+        Self {
+            pixels_per_unit_x: (x / METERS_PER_INCH).round() as u32,
+            pixels_per_unit_y: (y / METERS_PER_INCH).round() as u32,
+            unit: Unit::Meter,
+        }
+    }
+}
+
+impl TypedChunk for PhysicalDimensions {
+    const CHUNK_TYPE: [u8; 4] = *b"pHYs";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, Self::LENGTH)?;
+        let unit = match data[8] {
+            0 => Unit::Unknown,
+            1 => Unit::Meter,
+            unit => return Err(Self::invalid(format_args!("unknown unit {unit}"))),
+        };
+        Ok(Self {
+            pixels_per_unit_x: be_u32(data, 0),
+            pixels_per_unit_y: be_u32(data, 4),
+            unit,
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = self.pixels_per_unit_x.to_be_bytes().to_vec();
+        data.extend(self.pixels_per_unit_y.to_be_bytes());
+        data.push(self.unit as u8);
+        data
+    }
+}
+
+impl Display for PhysicalDimensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.dpi() {
+            Some((x, y)) => write!(
+                f,
+                "{}x{} pixels per meter ({x:.0}x{y:.0} DPI)",
+                self.pixels_per_unit_x, self.pixels_per_unit_y
+            ),
+            None => write!(
+                f,
+                "pixel aspect ratio {}:{}",
+                self.pixels_per_unit_x, self.pixels_per_unit_y
+            ),
+        }
+    }
+}
+
+impl Png {
+    /// Parses the pHYs chunk, if there is one.
+    pub(crate) fn physical_dimensions(&self) -> Option<Result<PhysicalDimensions, PngError>> {
+        self.find::<PhysicalDimensions>()
+    }
+
+    /// Replaces the pHYs chunk in place, or inserts it before
+    /// the first IDAT as the spec requires.
+    pub(crate) fn set_physical_dimensions(&mut self, dimensions: &PhysicalDimensions) {
+        let index = self
+            .chunks()
+            .iter()
+            .position(|chunk| matches!(&chunk.chunk_type().bytes(), b"pHYs" | b"IDAT" | b"IEND"))
+            .unwrap_or(self.chunks().len());
+        self.remove_chunks_where(|chunk| {
+            chunk.chunk_type().bytes() == PhysicalDimensions::CHUNK_TYPE
+        });
+        self.insert_chunk(index, dimensions.to_chunk());
+    }
+}
+
+#[cfg(test)]
+mod dimensions_tests {
+    use super::*;
+
+    #[test]
+    fn test_physical_dimensions_round_trip() {
+        let data = [0, 0, 0x0B, 0x13, 0, 0, 0x0B, 0x13, 1];
+        let dimensions = PhysicalDimensions::from_data(&data).unwrap();
+        assert_eq!(dimensions.pixels_per_unit_x, 2835);
+        assert_eq!(dimensions.unit, Unit::Meter);
+        assert_eq!(dimensions.to_data(), data);
+        assert_eq!(
+            dimensions.to_string(),
+            "2835x2835 pixels per meter (72x72 DPI)"
+        );
+    }
+
+    #[test]
+    fn test_physical_dimensions_dpi() {
+        let dimensions = PhysicalDimensions::from_dpi(300.0, 150.0);
+        assert_eq!(dimensions.pixels_per_unit_x, 11811);
+        assert_eq!(dimensions.pixels_per_unit_y, 5906);
+        let (x, y) = dimensions.dpi().unwrap();
+        assert!((x - 300.0).abs() < 0.05 && (y - 150.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_physical_dimensions_unknown_unit() {
+        let dimensions = PhysicalDimensions::from_data(&[0, 0, 0, 2, 0, 0, 0, 1, 0]).unwrap();
+        assert!(dimensions.dpi().is_none());
+        assert_eq!(dimensions.to_string(), "pixel aspect ratio 2:1");
+    }
+
+    #[test]
+    fn test_physical_dimensions_rejects_bad_data() {
+        assert!(PhysicalDimensions::from_data(&[0; 8]).is_err());
+        assert!(PhysicalDimensions::from_data(&[0, 0, 0, 1, 0, 0, 0, 1, 2]).is_err());
+    }
+}
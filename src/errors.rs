@@ -1,4 +1,7 @@
-use std::{array::TryFromSliceError, num::TryFromIntError, string::FromUtf8Error};
+use std::{
+    array::TryFromSliceError, fmt::Display, num::TryFromIntError, path::PathBuf,
+    string::FromUtf8Error,
+};
 
 use thiserror::Error;
 
@@ -8,12 +11,48 @@ pub(crate) struct Expectations {
     pub(crate) expected: u32,
 }
 
+/// Where in a PNG file a parse error happened.
+#[derive(Debug)]
+pub(crate) struct ChunkContext {
+    /// The zero-based index of the chunk in the file.
+    pub(crate) index: usize,
+    /// The chunk type code, if it could be read.
+    /// Invalid bytes are kept as escapes so they show up in messages.
+    pub(crate) chunk_type: Option<String>,
+    /// The byte offset of the chunk's length field in the file.
+    pub(crate) offset: usize,
+}
+
+impl ChunkContext {
+    pub(crate) fn new(index: usize, chunk_type: Option<[u8; 4]>, offset: usize) -> Self {
+        Self {
+            index,
+            chunk_type: chunk_type.map(|bytes| bytes.escape_ascii().to_string()),
+            offset,
+        }
+    }
+}
+
+impl Display for ChunkContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "chunk #{}", self.index)?;
+        if let Some(chunk_type) = &self.chunk_type {
+            write!(f, " '{chunk_type}'")?;
+        }
+        write!(f, " at offset {:#X}", self.offset)
+    }
+}
+
 #[derive(Error, Debug)]
 pub(crate) enum PngError {
     #[error(
         "error: chunk type code must be valid uppercase or lowercase ASCII letters ('A'-'Z' and 'a'-'z')"
     )]
     InvalidByte,
+    #[error(
+        "error: chunk type '{0}' has an invalid reserved bit (the third letter must be uppercase)"
+    )]
+    InvalidReservedBit(String),
     #[error("error {0}: failed to construct chunk type from string slice")]
     TryFromStrError(#[from] TryFromSliceError),
     #[error("error occurred while interpreting chunk data as string: {}", .0.utf8_error())]
@@ -22,10 +61,108 @@ pub(crate) enum PngError {
     ReadFromByteSlice(#[from] std::io::Error),
     #[error("error {0}: could not convert integer from u32 to usize")]
     TryUsizeFromU32(#[from] TryFromIntError),
-    #[error("error: invalid CRC {}, expected {}", .0.got, .0.expected)]
+    #[error("error: invalid CRC (got {}, expected {})", .0.got, .0.expected)]
     InvalidCrc(Expectations),
     #[error("error: invalid PNG file signature")]
     InvalidPngSignature,
+    #[error("error: unexpected end of file: needed {needed} bytes at offset {offset:#X}")]
+    UnexpectedEof { offset: usize, needed: usize },
+    #[error(
+        "error: chunk at offset {offset:#X} declares {length} data bytes but only {available} bytes remain in the file"
+    )]
+    ChunkLengthExceedsFile {
+        offset: usize,
+        length: u32,
+        available: usize,
+    },
+    #[error("error: chunk length {0} exceeds the maximum of 2^31-1 bytes")]
+    ChunkLengthTooLarge(u32),
+    #[error("{source} in {context}")]
+    InChunk {
+        context: ChunkContext,
+        source: Box<PngError>,
+    },
     #[error("error: chunk not found")]
     ChunkNotFound,
+    #[error("error occurred while writing PNG data: {0}")]
+    Write(std::io::Error),
+    #[error("error: a chunk is already being written")]
+    ChunkAlreadyOpen,
+    #[error("error: no chunk is being written")]
+    NoOpenChunk,
+    #[error("error: chunk declared {declared} data bytes but {written} were written")]
+    ChunkDataLength { declared: u32, written: u64 },
+    #[error("error: invalid {chunk_type} chunk: {reason}")]
+    InvalidChunkData { chunk_type: String, reason: String },
+    #[error("error: expected a {expected} chunk, got '{got}'")]
+    UnexpectedChunkType { expected: String, got: String },
+    #[error("error: the first chunk must be IHDR")]
+    IhdrNotFirst,
+    #[error("error: invalid zlib stream: {0}")]
+    InvalidZlibStream(&'static str),
+    #[error("error: {0} file(s) failed lint")]
+    LintFailed(usize),
+    #[error("error: {0}")]
+    InvalidStripPolicy(String),
+    #[error("error: could not access file {}: {source}", .path.display())]
+    FileAccess {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl PngError {
+    /// Attaches the location of the chunk being parsed to the error.
+    pub(crate) fn in_chunk(self, context: ChunkContext) -> Self {
+        Self::InChunk {
+            context,
+            source: Box::new(self),
+        }
+    }
+
+    /// Returns the underlying error, without any chunk location.
+    #[cfg(test)]
+    pub(crate) fn root(&self) -> &Self {
+        match self {
+            Self::InChunk { source, .. } => source.root(),
+            error => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod errors_tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_context_display() {
+        let context = ChunkContext::new(12, Some(*b"tEXt"), 0x1F4);
+        assert_eq!(context.to_string(), "chunk #12 'tEXt' at offset 0x1F4");
+    }
+
+    #[test]
+    fn test_chunk_context_display_without_type() {
+        let context = ChunkContext::new(3, None, 0x20);
+        assert_eq!(context.to_string(), "chunk #3 at offset 0x20");
+    }
+
+    #[test]
+    fn test_chunk_context_display_escapes_invalid_bytes() {
+        let context = ChunkContext::new(0, Some([b'R', 0, b'S', b't']), 8);
+        assert_eq!(context.to_string(), "chunk #0 'R\\x00St' at offset 0x8");
+    }
+
+    #[test]
+    fn test_in_chunk_display_and_root() {
+        let error = PngError::InvalidCrc(Expectations {
+            got: 1,
+            expected: 2,
+        })
+        .in_chunk(ChunkContext::new(12, Some(*b"tEXt"), 0x1F4));
+        assert_eq!(
+            error.to_string(),
+            "error: invalid CRC (got 1, expected 2) in chunk #12 'tEXt' at offset 0x1F4"
+        );
+        assert!(matches!(error.root(), PngError::InvalidCrc(_)));
+    }
 }
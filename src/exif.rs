@@ -0,0 +1,481 @@
+use std::{collections::HashSet, fmt::Display};
+
+use crate::{errors::PngError, png::Png, typed_chunk::TypedChunk};
+
+/// The byte order of the TIFF structure EXIF data is stored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    /// `II`, as written by Intel.
+    LittleEndian,
+    /// `MM`, as written by Motorola.
+    BigEndian,
+}
+
+impl ByteOrder {
+    fn u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::LittleEndian => u16::from_le_bytes(bytes),
+            Self::BigEndian => u16::from_be_bytes(bytes),
+        }
+    }
+
+    fn u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Self::LittleEndian => u32::from_le_bytes(bytes),
+            Self::BigEndian => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+impl Display for ByteOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::LittleEndian => "little-endian",
+            Self::BigEndian => "big-endian",
+        })
+    }
+}
+
+/// The image file directories a tag can come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Ifd {
+    /// The main image's IFD, with the camera and software tags.
+    Primary,
+    /// The Exif sub-IFD, with the capture settings and timestamps.
+    Exif,
+    /// The GPS sub-IFD, with the location.
+    Gps,
+}
+
+impl Display for Ifd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Primary => "IFD0",
+            Self::Exif => "Exif",
+            Self::Gps => "GPS",
+        })
+    }
+}
+
+/// A decoded tag value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ExifValue {
+    /// ASCII, without the trailing NULs.
+    Text(String),
+    /// BYTE, SHORT or LONG.
+    Unsigned(Vec<u32>),
+    /// SBYTE, SSHORT or SLONG.
+    Signed(Vec<i32>),
+    /// RATIONAL, as numerator and denominator.
+    Rational(Vec<(u32, u32)>),
+    /// SRATIONAL, as numerator and denominator.
+    SignedRational(Vec<(i32, i32)>),
+    /// UNDEFINED, FLOAT or DOUBLE, left undecoded.
+    Bytes(Vec<u8>),
+}
+
+impl Display for ExifValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn join<T>(
+            f: &mut std::fmt::Formatter<'_>,
+            values: &[T],
+            show: impl Fn(&T) -> String,
+        ) -> std::fmt::Result {
+            let values: Vec<String> = values.iter().map(show).collect();
+            f.write_str(&values.join(", "))
+        }
+        match self {
+            Self::Text(text) => write!(f, "{text:?}"),
+            Self::Unsigned(values) => join(f, values, u32::to_string),
+            Self::Signed(values) => join(f, values, i32::to_string),
+            Self::Rational(values) => join(f, values, |(n, d)| format!("{n}/{d}")),
+            Self::SignedRational(values) => join(f, values, |(n, d)| format!("{n}/{d}")),
+            Self::Bytes(bytes) if bytes.len() <= 16 => write!(f, "{}", bytes.escape_ascii()),
+            Self::Bytes(bytes) => write!(f, "{} bytes", bytes.len()),
+        }
+    }
+}
+
+/// One tag of the EXIF data.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ExifEntry {
+    pub(crate) ifd: Ifd,
+    pub(crate) tag: u16,
+    pub(crate) value: ExifValue,
+}
+
+impl ExifEntry {
+    /// The tag's name, for the camera, orientation, location and
+    /// timestamp tags privacy reviews care about.
+    pub(crate) fn name(&self) -> Option<&'static str> {
+        let name = match (self.ifd, self.tag) {
+            (Ifd::Gps, 0x0000) => "GPSVersionID",
+            (Ifd::Gps, 0x0001) => "GPSLatitudeRef",
+            (Ifd::Gps, 0x0002) => "GPSLatitude",
+            (Ifd::Gps, 0x0003) => "GPSLongitudeRef",
+            (Ifd::Gps, 0x0004) => "GPSLongitude",
+            (Ifd::Gps, 0x0005) => "GPSAltitudeRef",
+            (Ifd::Gps, 0x0006) => "GPSAltitude",
+            (Ifd::Gps, 0x0007) => "GPSTimeStamp",
+            (Ifd::Gps, 0x001D) => "GPSDateStamp",
+            (Ifd::Gps, _) => return None,
+            (_, 0x010E) => "ImageDescription",
+            (_, 0x010F) => "Make",
+            (_, 0x0110) => "Model",
+            (_, 0x0112) => "Orientation",
+            (_, 0x0131) => "Software",
+            (_, 0x0132) => "DateTime",
+            (_, 0x013B) => "Artist",
+            (_, 0x8298) => "Copyright",
+            (_, 0x9003) => "DateTimeOriginal",
+            (_, 0x9004) => "DateTimeDigitized",
+            (_, 0x9010) => "OffsetTime",
+            (_, 0x9011) => "OffsetTimeOriginal",
+            (_, 0xA420) => "ImageUniqueID",
+            (_, 0xA430) => "CameraOwnerName",
+            (_, 0xA431) => "BodySerialNumber",
+            (_, 0xA433) => "LensMake",
+            (_, 0xA434) => "LensModel",
+            (_, 0xA435) => "LensSerialNumber",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+impl Display for ExifEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}/{name}: {}", self.ifd, self.value),
+            None => write!(f, "{}/{:#06x}: {}", self.ifd, self.tag, self.value),
+        }
+    }
+}
+
+/// The tag pointing to the Exif sub-IFD.
+const EXIF_IFD_POINTER: u16 = 0x8769;
+/// The tag pointing to the GPS sub-IFD.
+const GPS_IFD_POINTER: u16 = 0x8825;
+
+/// Reads values out of the TIFF structure, bounds checking every access.
+struct TiffReader<'a> {
+    data: &'a [u8],
+    byte_order: ByteOrder,
+}
+
+impl<'a> TiffReader<'a> {
+    fn bytes(&self, offset: usize, length: usize) -> Result<&'a [u8], PngError> {
+        offset
+            .checked_add(length)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| {
+                ExifChunk::invalid(format_args!(
+                    "{length} bytes at offset {offset} are out of bounds"
+                ))
+            })
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, PngError> {
+        let bytes = self.bytes(offset, 2)?;
+        Ok(self.byte_order.u16([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, PngError> {
+        let bytes = self.bytes(offset, 4)?;
+        Ok(self
+            .byte_order
+            .u32([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Reads the entries of the IFD at `offset`, following the pointers
+    /// to the Exif and GPS sub-IFDs.
+    fn read_ifd(
+        &self,
+        ifd: Ifd,
+        offset: usize,
+        visited: &mut HashSet<usize>,
+        entries: &mut Vec<ExifEntry>,
+    ) -> Result<(), PngError> {
+        if !visited.insert(offset) {
+            return Err(ExifChunk::invalid("IFDs point to each other in a loop"));
+        }
+        let count = self.u16(offset)?;
+        for index in 0..usize::from(count) {
+            let entry_offset = offset + 2 + index * 12;
+            let tag = self.u16(entry_offset)?;
+            let field_type = self.u16(entry_offset + 2)?;
+            let count = self.u32(entry_offset + 4)? as usize;
+            let sub_ifd = match (ifd, tag) {
+                (Ifd::Primary, EXIF_IFD_POINTER) => Some(Ifd::Exif),
+                (Ifd::Primary, GPS_IFD_POINTER) => Some(Ifd::Gps),
+                _ => None,
+            };
+            if let Some(sub_ifd) = sub_ifd {
+                let sub_offset = self.u32(entry_offset + 8)? as usize;
+                self.read_ifd(sub_ifd, sub_offset, visited, entries)?;
+                continue;
+            }
+            // Readers must skip field types they don't know.
+            let Some(size) = field_size(field_type) else {
+                continue;
+            };
+            let length = count
+                .checked_mul(size)
+                .ok_or_else(|| ExifChunk::invalid(format_args!("tag {tag:#06x} is too long")))?;
+            let value_offset = if length <= 4 {
+                entry_offset + 8
+            } else {
+                self.u32(entry_offset + 8)? as usize
+            };
+            let bytes = self.bytes(value_offset, length)?;
+            entries.push(ExifEntry {
+                ifd,
+                tag,
+                value: self.value(field_type, bytes),
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes a value of `field_type`, whose size `bytes` is a multiple of.
+    fn value(&self, field_type: u16, bytes: &[u8]) -> ExifValue {
+        let order = self.byte_order;
+        let u16s = || bytes.chunks_exact(2).map(|b| order.u16([b[0], b[1]]));
+        let u32s = || {
+            bytes
+                .chunks_exact(4)
+                .map(|b| order.u32([b[0], b[1], b[2], b[3]]))
+        };
+        let pairs = |values: Vec<u32>| -> Vec<(u32, u32)> {
+            values
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .collect()
+        };
+        match field_type {
+            1 => ExifValue::Unsigned(bytes.iter().map(|&b| u32::from(b)).collect()),
+            2 => ExifValue::Text(
+                String::from_utf8_lossy(bytes)
+                    .trim_end_matches('\0')
+                    .to_string(),
+            ),
+            3 => ExifValue::Unsigned(u16s().map(u32::from).collect()),
+            4 => ExifValue::Unsigned(u32s().collect()),
+            5 => ExifValue::Rational(pairs(u32s().collect())),
+            6 => ExifValue::Signed(bytes.iter().map(|&b| i32::from(b as i8)).collect()),
+            8 => ExifValue::Signed(u16s().map(|v| i32::from(v as i16)).collect()),
+            9 => ExifValue::Signed(u32s().map(|v| v as i32).collect()),
+            10 => ExifValue::SignedRational(
+                pairs(u32s().collect())
+                    .into_iter()
+                    .map(|(n, d)| (n as i32, d as i32))
+                    .collect(),
+            ),
+            _ => ExifValue::Bytes(bytes.to_vec()),
+        }
+    }
+}
+
+/// The size in bytes of one value of a TIFF field type.
+fn field_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+/// EXIF metadata (`eXIf`), such as the camera, orientation and location
+/// the photo was taken with. The data is a TIFF structure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExifChunk {
+    data: Vec<u8>,
+    byte_order: ByteOrder,
+}
+
+impl ExifChunk {
+    pub(crate) fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    fn reader(&self) -> TiffReader<'_> {
+        TiffReader {
+            data: &self.data,
+            byte_order: self.byte_order,
+        }
+    }
+
+    /// Lists the tags of the primary image and its Exif and GPS
+    /// sub-IFDs, in file order. Thumbnail IFDs are skipped.
+    pub(crate) fn entries(&self) -> Result<Vec<ExifEntry>, PngError> {
+        let reader = self.reader();
+        let mut entries = Vec::new();
+        let first_ifd = reader.u32(4)? as usize;
+        reader.read_ifd(Ifd::Primary, first_ifd, &mut HashSet::new(), &mut entries)?;
+        Ok(entries)
+    }
+}
+
+impl TypedChunk for ExifChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"eXIf";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let byte_order = match data.get(..4) {
+            Some(b"II*\0") => ByteOrder::LittleEndian,
+            Some(b"MM\0*") => ByteOrder::BigEndian,
+            _ => return Err(Self::invalid("data must start with a TIFF header")),
+        };
+        if data.len() < 8 {
+            return Err(Self::invalid("TIFF header is cut short"));
+        }
+        Ok(Self {
+            data: data.to_vec(),
+            byte_order,
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+}
+
+impl Display for ExifChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.entries() {
+            Ok(entries) => write!(f, "EXIF, {}, {} tags", self.byte_order, entries.len()),
+            Err(error) => write!(f, "EXIF, {}, {error}", self.byte_order),
+        }
+    }
+}
+
+impl Png {
+    /// Parses the EXIF data, if there is any.
+    pub(crate) fn exif(&self) -> Option<Result<ExifChunk, PngError>> {
+        self.find::<ExifChunk>()
+    }
+
+    /// Removes every eXIf chunk, returning how many there were.
+    pub(crate) fn strip_exif(&mut self) -> usize {
+        self.remove_chunks_where(|chunk| chunk.chunk_type().bytes() == ExifChunk::CHUNK_TYPE)
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod exif_tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{chunk::Chunk, chunk_type::ChunkType};
+
+    /// Builds a TIFF structure with Make, Orientation and a GPS sub-IFD
+    /// holding the latitude.
+    fn testing_tiff(byte_order: ByteOrder) -> Vec<u8> {
+        let u16 = |value: u16| match byte_order {
+            ByteOrder::LittleEndian => value.to_le_bytes().to_vec(),
+            ByteOrder::BigEndian => value.to_be_bytes().to_vec(),
+        };
+        let u32 = |value: u32| match byte_order {
+            ByteOrder::LittleEndian => value.to_le_bytes().to_vec(),
+            ByteOrder::BigEndian => value.to_be_bytes().to_vec(),
+        };
+        let entry = |tag, field_type, count, value: Vec<u8>| {
+            [u16(tag), u16(field_type), u32(count), value].concat()
+        };
+        let mut tiff = match byte_order {
+            ByteOrder::LittleEndian => b"II*\0".to_vec(),
+            ByteOrder::BigEndian => b"MM\0*".to_vec(),
+        };
+        tiff.extend(u32(8));
+        // IFD0 at 8: 3 entries, then the next IFD offset, ends at 50.
+        tiff.extend(u16(3));
+        tiff.extend(entry(0x010F, 2, 6, u32(50)));
+        tiff.extend(entry(0x0112, 3, 1, [u16(6), u16(0)].concat()));
+        tiff.extend(entry(GPS_IFD_POINTER, 4, 1, u32(56)));
+        tiff.extend(u32(0));
+        tiff.extend(b"Canon\0");
+        // GPS IFD at 56: 2 entries, ends at 86.
+        tiff.extend(u16(2));
+        tiff.extend(entry(0x0001, 2, 2, b"N\0\0\0".to_vec()));
+        tiff.extend(entry(0x0002, 5, 3, u32(86)));
+        tiff.extend(u32(0));
+        for value in [51, 1, 30, 1, 2634, 100] {
+            tiff.extend(u32(value));
+        }
+        tiff
+    }
+
+    #[test]
+    fn test_entries_in_both_byte_orders() {
+        for byte_order in [ByteOrder::LittleEndian, ByteOrder::BigEndian] {
+            let exif = ExifChunk::from_data(&testing_tiff(byte_order)).unwrap();
+            assert_eq!(exif.byte_order(), byte_order);
+            let entries: Vec<String> = exif
+                .entries()
+                .unwrap()
+                .iter()
+                .map(ExifEntry::to_string)
+                .collect();
+            assert_eq!(
+                entries,
+                [
+                    "IFD0/Make: \"Canon\"",
+                    "IFD0/Orientation: 6",
+                    "GPS/GPSLatitudeRef: \"N\"",
+                    "GPS/GPSLatitude: 51/1, 30/1, 2634/100",
+                ]
+            );
+            assert_eq!(exif.to_string(), format!("EXIF, {byte_order}, 4 tags"));
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_tiff_data() {
+        assert!(ExifChunk::from_data(b"Exif\0\0II*\0").is_err());
+        assert!(ExifChunk::from_data(b"II*\0").is_err());
+    }
+
+    #[test]
+    fn test_out_of_bounds_entries() {
+        let mut tiff = testing_tiff(ByteOrder::BigEndian);
+        tiff.truncate(90);
+        let exif = ExifChunk::from_data(&tiff).unwrap();
+        assert!(matches!(
+            exif.entries(),
+            Err(PngError::InvalidChunkData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ifd_loop() {
+        let mut tiff = testing_tiff(ByteOrder::BigEndian);
+        // Point the GPS IFD back at IFD0.
+        tiff[8 + 2 + 2 * 12 + 8..8 + 2 + 3 * 12].copy_from_slice(&8u32.to_be_bytes());
+        let exif = ExifChunk::from_data(&tiff).unwrap();
+        assert!(exif.entries().is_err());
+    }
+
+    #[test]
+    fn test_unknown_tags_and_types() {
+        let entry = ExifEntry {
+            ifd: Ifd::Exif,
+            tag: 0x9286,
+            value: ExifValue::Bytes(vec![0; 20]),
+        };
+        assert_eq!(entry.to_string(), "Exif/0x9286: 20 bytes");
+        assert_eq!(field_size(13), None);
+    }
+
+    #[test]
+    fn test_png_strip_exif() {
+        let exif = ExifChunk::from_data(&testing_tiff(ByteOrder::LittleEndian)).unwrap();
+        let iend = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        let mut png = Png::from_chunks(vec![exif.to_chunk(), exif.to_chunk(), iend]);
+        assert_eq!(png.exif().unwrap().unwrap(), exif);
+        assert_eq!(png.strip_exif(), 2);
+        assert!(png.exif().is_none());
+        assert_eq!(png.strip_exif(), 0);
+    }
+}
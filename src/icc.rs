@@ -0,0 +1,263 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::PngError,
+    png::Png,
+    text::{check_keyword, split_keyword, string_to_latin1},
+    typed_chunk::{be_u32, TypedChunk},
+    zlib,
+};
+
+/// The fields of an ICC profile header that say what the profile is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IccHeader {
+    /// The profile size in bytes, as declared by the header.
+    pub(crate) size: u32,
+    /// The major, minor and bug fix version of the ICC spec.
+    pub(crate) version: (u8, u8, u8),
+    /// The kind of device the profile is for, e.g. `mntr`.
+    pub(crate) device_class: [u8; 4],
+    /// The color space of the image data, e.g. `RGB `.
+    pub(crate) color_space: [u8; 4],
+}
+
+impl IccHeader {
+    /// The length of the header at the start of every profile.
+    pub(crate) const LENGTH: usize = 128;
+    /// The profile file signature, `acsp`.
+    const SIGNATURE: [u8; 4] = *b"acsp";
+    const SIGNATURE_OFFSET: usize = 36;
+
+    /// Parses the header of `profile`, checking the signature
+    /// and that the declared size matches.
+    pub(crate) fn parse(profile: &[u8]) -> Result<Self, PngError> {
+        if profile.len() < Self::LENGTH {
+            return Err(IccProfileChunk::invalid(format_args!(
+                "profile is {} bytes, too short for an ICC header",
+                profile.len()
+            )));
+        }
+        let signature = &profile[Self::SIGNATURE_OFFSET..Self::SIGNATURE_OFFSET + 4];
+        if signature != Self::SIGNATURE {
+            return Err(IccProfileChunk::invalid("profile has no ICC signature"));
+        }
+        let size = be_u32(profile, 0);
+        if usize::try_from(size).ok() != Some(profile.len()) {
+            return Err(IccProfileChunk::invalid(format_args!(
+                "profile header declares {size} bytes, but the profile has {}",
+                profile.len()
+            )));
+        }
+        let four_bytes =
+            |offset: usize| <[u8; 4]>::try_from(&profile[offset..offset + 4]).expect("4 bytes");
+        Ok(Self {
+            size,
+            version: (profile[8], profile[9] >> 4, profile[9] & 0x0F),
+            device_class: four_bytes(12),
+            color_space: four_bytes(16),
+        })
+    }
+
+    /// Describes the device class, falling back to the raw signature.
+    fn device_class_name(&self) -> String {
+        match &self.device_class {
+            b"scnr" => "input device".to_string(),
+            b"mntr" => "display device".to_string(),
+            b"prtr" => "output device".to_string(),
+            b"link" => "device link".to_string(),
+            b"spac" => "color space".to_string(),
+            b"abst" => "abstract".to_string(),
+            b"nmcl" => "named color".to_string(),
+            other => format!("'{}'", other.escape_ascii()),
+        }
+    }
+}
+
+impl Display for IccHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (major, minor, bug_fix) = self.version;
+        write!(
+            f,
+            "ICC v{major}.{minor}.{bug_fix} {} profile, {} color space, {} bytes",
+            self.device_class_name(),
+            self.color_space.trim_ascii_end().escape_ascii(),
+            self.size
+        )
+    }
+}
+
+/// An embedded ICC color profile (`iCCP`), stored compressed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IccProfileChunk {
+    name: String,
+    profile: Vec<u8>,
+}
+
+impl IccProfileChunk {
+    /// The only compression method the spec defines: zlib.
+    pub(crate) const COMPRESSION_METHOD: u8 = 0;
+
+    /// Creates an iCCP chunk holding the uncompressed `profile`.
+    /// The name follows the same rules as a text keyword.
+    pub(crate) fn new(name: &str, profile: Vec<u8>) -> Result<Self, PngError> {
+        check_keyword::<Self>(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            profile,
+        })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the uncompressed profile.
+    pub(crate) fn profile(&self) -> &[u8] {
+        &self.profile
+    }
+
+    /// Parses the header of the profile.
+    pub(crate) fn header(&self) -> Result<IccHeader, PngError> {
+        IccHeader::parse(&self.profile)
+    }
+}
+
+impl TypedChunk for IccProfileChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"iCCP";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let (name, rest) = split_keyword::<Self>(data)?;
+        let (&method, compressed) = rest
+            .split_first()
+            .ok_or_else(|| Self::invalid("missing compression method"))?;
+        if method != Self::COMPRESSION_METHOD {
+            return Err(Self::invalid(format_args!(
+                "unknown compression method {method}"
+            )));
+        }
+        Ok(Self {
+            name,
+            profile: zlib::decompress(compressed)?,
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = string_to_latin1(&self.name).expect("name is checked to be Latin-1");
+        data.extend([0, Self::COMPRESSION_METHOD]);
+        data.extend(zlib::compress(&self.profile));
+        data
+    }
+}
+
+impl Display for IccProfileChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.header() {
+            Ok(header) => write!(f, "{}: {header}", self.name),
+            Err(error) => write!(f, "{}: {error}", self.name),
+        }
+    }
+}
+
+impl Png {
+    /// Parses the embedded ICC profile, if there is one.
+    pub(crate) fn icc_profile(&self) -> Option<Result<IccProfileChunk, PngError>> {
+        self.find::<IccProfileChunk>()
+    }
+
+    /// Embeds `profile` in place of any existing one, before PLTE and IDAT
+    /// as the spec requires. An sRGB chunk would contradict the profile,
+    /// so any are removed; returns whether there were some.
+    pub(crate) fn set_icc_profile(&mut self, profile: IccProfileChunk) -> bool {
+        let removed = self
+            .remove_chunks_where(|chunk| matches!(&chunk.chunk_type().bytes(), b"iCCP" | b"sRGB"));
+        let index = self
+            .chunks()
+            .iter()
+            .position(|chunk| matches!(&chunk.chunk_type().bytes(), b"PLTE" | b"IDAT" | b"IEND"))
+            .unwrap_or(self.chunks().len());
+        self.insert_chunk(index, profile.to_chunk());
+        removed
+            .iter()
+            .any(|chunk| chunk.chunk_type().bytes() == *b"sRGB")
+    }
+}
+
+#[cfg(test)]
+mod icc_tests {
+    use super::*;
+    use crate::test_utils::chunk;
+
+    /// A bare display RGB profile header, padded to `size` bytes.
+    fn testing_profile(size: u32) -> Vec<u8> {
+        let mut profile = vec![0; size as usize];
+        profile[..4].copy_from_slice(&size.to_be_bytes());
+        profile[8..10].copy_from_slice(&[4, 0x30]);
+        profile[12..16].copy_from_slice(b"mntr");
+        profile[16..20].copy_from_slice(b"RGB ");
+        profile[36..40].copy_from_slice(b"acsp");
+        profile
+    }
+
+    #[test]
+    fn test_icc_header() {
+        let header = IccHeader::parse(&testing_profile(200)).unwrap();
+        assert_eq!(header.size, 200);
+        assert_eq!(header.version, (4, 3, 0));
+        assert_eq!(&header.device_class, b"mntr");
+        assert_eq!(
+            header.to_string(),
+            "ICC v4.3.0 display device profile, RGB color space, 200 bytes"
+        );
+    }
+
+    #[test]
+    fn test_icc_header_checks() {
+        assert!(IccHeader::parse(&[0; 100]).is_err());
+        assert!(IccHeader::parse(&[0; 128]).is_err());
+        let mut profile = testing_profile(128);
+        profile.push(0);
+        assert!(IccHeader::parse(&profile).is_err());
+    }
+
+    #[test]
+    fn test_icc_profile_chunk_round_trip() {
+        let iccp = IccProfileChunk::new("Display P3", testing_profile(256)).unwrap();
+        let data = iccp.to_data();
+        assert!(data.starts_with(b"Display P3\0\0\x78"));
+        let parsed = IccProfileChunk::from_data(&data).unwrap();
+        assert_eq!(parsed, iccp);
+        assert_eq!(parsed.name(), "Display P3");
+        assert_eq!(
+            parsed.to_string(),
+            "Display P3: ICC v4.3.0 display device profile, RGB color space, 256 bytes"
+        );
+    }
+
+    #[test]
+    fn test_icc_profile_chunk_checks() {
+        assert!(IccProfileChunk::new("", Vec::new()).is_err());
+        assert!(IccProfileChunk::from_data(b"name\0").is_err());
+        assert!(IccProfileChunk::from_data(b"name\0\x01\x78\x9C").is_err());
+        assert!(IccProfileChunk::from_data(b"name\0\0not zlib").is_err());
+    }
+
+    #[test]
+    fn test_png_set_icc_profile() {
+        let mut png = Png::from_chunks(vec![
+            chunk("IHDR"),
+            chunk("sRGB"),
+            chunk("IDAT"),
+            chunk("IEND"),
+        ]);
+        let iccp = IccProfileChunk::new("ICC profile", testing_profile(128)).unwrap();
+        assert!(png.set_icc_profile(iccp.clone()));
+        assert!(!png.set_icc_profile(iccp.clone()));
+        let types: Vec<String> = png
+            .chunks()
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(types, ["IHDR", "iCCP", "IDAT", "IEND"]);
+        assert_eq!(png.icc_profile().unwrap().unwrap(), iccp);
+    }
+}
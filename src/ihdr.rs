@@ -0,0 +1,319 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::PngError,
+    png::Png,
+    typed_chunk::{be_u32, TypedChunk},
+};
+
+/// How the pixels of an image are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorType {
+    Grayscale = 0,
+    Rgb = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    Rgba = 6,
+}
+
+impl ColorType {
+    /// The bit depths the spec allows for this color type.
+    pub(crate) fn bit_depths(self) -> &'static [u8] {
+        match self {
+            Self::Grayscale => &[1, 2, 4, 8, 16],
+            Self::Indexed => &[1, 2, 4, 8],
+            Self::Rgb | Self::GrayscaleAlpha | Self::Rgba => &[8, 16],
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = PngError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::Rgb),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::Rgba),
+            _ => Err(Ihdr::invalid(format_args!("unknown color type {value}"))),
+        }
+    }
+}
+
+impl Display for ColorType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Grayscale => "grayscale",
+            Self::Rgb => "RGB",
+            Self::Indexed => "indexed",
+            Self::GrayscaleAlpha => "grayscale with alpha",
+            Self::Rgba => "RGBA",
+        })
+    }
+}
+
+/// The order in which pixels are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InterlaceMethod {
+    None = 0,
+    Adam7 = 1,
+}
+
+impl TryFrom<u8> for InterlaceMethod {
+    type Error = PngError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Adam7),
+            _ => Err(Ihdr::invalid(format_args!(
+                "unknown interlace method {value}"
+            ))),
+        }
+    }
+}
+
+/// The image header, which must be the first chunk of every PNG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Ihdr {
+    /// Width of the image in pixels, 1 to 2^31-1.
+    pub(crate) width: u32,
+    /// Height of the image in pixels, 1 to 2^31-1.
+    pub(crate) height: u32,
+    /// Bits per sample, or per palette index for indexed images.
+    pub(crate) bit_depth: u8,
+    pub(crate) color_type: ColorType,
+    /// Always 0 (deflate).
+    pub(crate) compression_method: u8,
+    /// Always 0 (adaptive filtering).
+    pub(crate) filter_method: u8,
+    pub(crate) interlace_method: InterlaceMethod,
+}
+
+impl Ihdr {
+    /// The length of the IHDR chunk data.
+    pub(crate) const LENGTH: usize = 13;
+    /// The largest width or height, 2^31-1.
+    pub(crate) const MAX_DIMENSION: u32 = (1 << 31) - 1;
+
+    /// Checks the header against the PNG spec.
+    pub(crate) fn validate(&self) -> Result<(), PngError> {
+        for (name, value) in [("width", self.width), ("height", self.height)] {
+            if value == 0 || value > Self::MAX_DIMENSION {
+                return Err(Self::invalid(format_args!(
+                    "{name} must be between 1 and 2^31-1, got {value}"
+                )));
+            }
+        }
+        if !self.color_type.bit_depths().contains(&self.bit_depth) {
+            return Err(Self::invalid(format_args!(
+                "bit depth {} is not allowed for {} images",
+                self.bit_depth, self.color_type
+            )));
+        }
+        if self.compression_method != 0 {
+            return Err(Self::invalid(format_args!(
+                "unknown compression method {}",
+                self.compression_method
+            )));
+        }
+        if self.filter_method != 0 {
+            return Err(Self::invalid(format_args!(
+                "unknown filter method {}",
+                self.filter_method
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl TypedChunk for Ihdr {
+    const CHUNK_TYPE: [u8; 4] = *b"IHDR";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, Self::LENGTH)?;
+        let ihdr = Self {
+            width: be_u32(data, 0),
+            height: be_u32(data, 4),
+            bit_depth: data[8],
+            color_type: ColorType::try_from(data[9])?,
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: InterlaceMethod::try_from(data[12])?,
+        };
+        ihdr.validate()?;
+        Ok(ihdr)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::LENGTH);
+        data.extend_from_slice(&self.width.to_be_bytes());
+        data.extend_from_slice(&self.height.to_be_bytes());
+        data.extend_from_slice(&[
+            self.bit_depth,
+            self.color_type as u8,
+            self.compression_method,
+            self.filter_method,
+            self.interlace_method as u8,
+        ]);
+        data
+    }
+}
+
+impl Display for Ihdr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{}, {}-bit {}",
+            self.width, self.height, self.bit_depth, self.color_type
+        )?;
+        if self.interlace_method == InterlaceMethod::Adam7 {
+            write!(f, ", Adam7 interlaced")?;
+        }
+        Ok(())
+    }
+}
+
+impl Png {
+    /// Parses and validates the image header.
+    ///
+    /// Fails if the first chunk isn't a valid IHDR, so anything that
+    /// isn't really an image is rejected.
+    pub(crate) fn ihdr(&self) -> Result<Ihdr, PngError> {
+        match self.chunks().first() {
+            Some(chunk) if chunk.chunk_type().bytes() == Ihdr::CHUNK_TYPE => {
+                Ihdr::from_chunk(chunk)
+            }
+            _ => Err(PngError::IhdrNotFirst),
+        }
+    }
+}
+
+#[cfg(test)]
+mod ihdr_tests {
+    use super::*;
+    use crate::test_utils::chunk;
+
+    fn get_testing_ihdr() -> Ihdr {
+        Ihdr {
+            width: 640,
+            height: 480,
+            bit_depth: 8,
+            color_type: ColorType::Rgba,
+            compression_method: 0,
+            filter_method: 0,
+            interlace_method: InterlaceMethod::None,
+        }
+    }
+
+    #[test]
+    fn test_ihdr_round_trip() {
+        let ihdr = get_testing_ihdr();
+        let chunk = ihdr.to_chunk();
+        assert_eq!(chunk.length(), 13);
+        assert_eq!(Ihdr::from_chunk(&chunk).unwrap(), ihdr);
+    }
+
+    #[test]
+    fn test_ihdr_from_bytes() {
+        let data = [0, 0, 0, 2, 0, 0, 0, 3, 16, 0, 0, 0, 1];
+        let ihdr = Ihdr::from_data(&data).unwrap();
+        assert_eq!((ihdr.width, ihdr.height), (2, 3));
+        assert_eq!(ihdr.bit_depth, 16);
+        assert_eq!(ihdr.color_type, ColorType::Grayscale);
+        assert_eq!(ihdr.interlace_method, InterlaceMethod::Adam7);
+    }
+
+    #[test]
+    fn test_ihdr_wrong_length() {
+        let mut data = get_testing_ihdr().to_data();
+        data.push(0);
+        assert!(matches!(
+            Ihdr::from_data(&data),
+            Err(PngError::InvalidChunkData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_ihdr_zero_dimensions() {
+        let ihdr = Ihdr {
+            height: 0,
+            ..get_testing_ihdr()
+        };
+        assert!(ihdr.validate().is_err());
+        assert!(Ihdr::from_data(&ihdr.to_data()).is_err());
+    }
+
+    #[test]
+    fn test_ihdr_invalid_bit_depth() {
+        for (color_type, bit_depth) in [
+            (ColorType::Indexed, 16),
+            (ColorType::Rgb, 4),
+            (ColorType::Grayscale, 3),
+        ] {
+            let ihdr = Ihdr {
+                bit_depth,
+                color_type,
+                ..get_testing_ihdr()
+            };
+            assert!(ihdr.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_ihdr_invalid_methods() {
+        let valid = get_testing_ihdr().to_data();
+        // Color type, compression, filter and interlace method.
+        for (offset, value) in [(9, 1), (10, 1), (11, 1), (12, 2)] {
+            let mut data = valid.clone();
+            data[offset] = value;
+            assert!(Ihdr::from_data(&data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ihdr_error_message() {
+        let ihdr = Ihdr {
+            bit_depth: 4,
+            color_type: ColorType::Rgb,
+            ..get_testing_ihdr()
+        };
+        assert_eq!(
+            ihdr.validate().unwrap_err().to_string(),
+            "error: invalid IHDR chunk: bit depth 4 is not allowed for RGB images"
+        );
+    }
+
+    #[test]
+    fn test_ihdr_display() {
+        let ihdr = Ihdr {
+            interlace_method: InterlaceMethod::Adam7,
+            ..get_testing_ihdr()
+        };
+        assert_eq!(ihdr.to_string(), "640x480, 8-bit RGBA, Adam7 interlaced");
+    }
+
+    #[test]
+    fn test_png_ihdr() {
+        let png = Png::from_chunks(vec![get_testing_ihdr().to_chunk(), chunk("IEND")]);
+        assert_eq!(png.ihdr().unwrap(), get_testing_ihdr());
+    }
+
+    #[test]
+    fn test_png_ihdr_not_first() {
+        let png = Png::from_chunks(vec![chunk("IEND"), get_testing_ihdr().to_chunk()]);
+        assert!(matches!(png.ihdr(), Err(PngError::IhdrNotFirst)));
+        let png = Png::from_chunks(Vec::new());
+        assert!(matches!(png.ihdr(), Err(PngError::IhdrNotFirst)));
+    }
+
+    #[test]
+    fn test_from_chunk_wrong_type() {
+        assert!(matches!(
+            Ihdr::from_chunk(&chunk("IEND")),
+            Err(PngError::UnexpectedChunkType { .. })
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::{errors::ChunkContext, png::Png};
+
+/// The critical chunk types defined by the PNG spec.
+const STANDARD_CRITICAL_CHUNKS: [[u8; 4]; 4] = [*b"IHDR", *b"PLTE", *b"IDAT", *b"IEND"];
+
+/// How serious a lint finding is, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub(crate) enum Severity {
+    /// Worth knowing, but not a problem.
+    Info,
+    /// Allowed by the spec, but likely to cause trouble.
+    Warning,
+    /// Breaks the spec.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// One problem found by `Png::lint`.
+#[derive(Debug)]
+pub(crate) struct Finding {
+    pub(crate) severity: Severity,
+    /// A short, stable name for the kind of problem, e.g. `missing-iend`.
+    pub(crate) code: &'static str,
+    pub(crate) message: String,
+}
+
+impl Finding {
+    /// Formats the finding as a JSON object.
+    pub(crate) fn to_json(&self) -> String {
+        format!(
+            r#"{{"severity":"{}","code":"{}","message":{}}}"#,
+            self.severity,
+            self.code,
+            json_string(&self.message)
+        )
+    }
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} [{}]", self.severity, self.message, self.code)
+    }
+}
+
+impl Png {
+    /// Checks the PNG against the spec and common practice.
+    ///
+    /// Every `Png::validate` violation is an error. Unknown critical chunks,
+    /// which viewers must refuse, and private chunks are warnings.
+    /// Ancillary chunks after the image data are noted as info,
+    /// since readers that stop at IDAT won't see them.
+    pub(crate) fn lint(&self) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .validate()
+            .into_iter()
+            .map(|violation| Finding {
+                severity: Severity::Error,
+                code: violation.code(),
+                message: violation.to_string(),
+            })
+            .collect();
+
+        let mut seen_idat = false;
+        for ((index, chunk), offset) in self.chunks().iter().enumerate().zip(self.chunk_offsets()) {
+            let chunk_type = chunk.chunk_type();
+            let context = ChunkContext::new(index, Some(chunk_type.bytes()), offset);
+            let mut finding = |severity, code, message| {
+                findings.push(Finding {
+                    severity,
+                    code,
+                    message: format!("{context}: {message}"),
+                })
+            };
+
+            if chunk_type.is_critical() && !STANDARD_CRITICAL_CHUNKS.contains(&chunk_type.bytes()) {
+                finding(
+                    Severity::Warning,
+                    "unknown-critical-chunk",
+                    "unknown critical chunk, viewers will refuse the image",
+                );
+            }
+            if !chunk_type.is_public() {
+                finding(Severity::Warning, "private-chunk", "private chunk");
+            }
+            if seen_idat && !chunk_type.is_critical() {
+                finding(
+                    Severity::Info,
+                    "ancillary-after-idat",
+                    "ancillary chunk after the image data",
+                );
+            }
+            seen_idat |= chunk_type.bytes() == *b"IDAT";
+        }
+        findings
+    }
+}
+
+/// Quotes and escapes `value` as a JSON string.
+pub(crate) fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod lint_tests {
+    use super::*;
+    use crate::{
+        ihdr::ColorType,
+        test_utils::{chunk, ihdr},
+        typed_chunk::TypedChunk,
+    };
+
+    fn testing_png(chunk_types: &[&str]) -> Png {
+        let mut chunks = vec![ihdr(ColorType::Rgb, 8).to_chunk()];
+        chunks.extend(chunk_types.iter().map(|chunk_type| chunk(chunk_type)));
+        Png::from_chunks(chunks)
+    }
+
+    fn codes(findings: &[Finding]) -> Vec<(Severity, &'static str)> {
+        findings
+            .iter()
+            .map(|finding| (finding.severity, finding.code))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_clean_png() {
+        let png = testing_png(&["gAMA", "IDAT", "IEND"]);
+        assert!(png.lint().is_empty());
+    }
+
+    #[test]
+    fn test_lint_errors_come_from_validate() {
+        let png = testing_png(&["IDAT"]);
+        assert_eq!(codes(&png.lint()), [(Severity::Error, "missing-iend")]);
+    }
+
+    #[test]
+    fn test_lint_warnings() {
+        let png = testing_png(&["ABCD", "IDAT", "ruSt", "IEND"]);
+        assert_eq!(
+            codes(&png.lint()),
+            [
+                (Severity::Warning, "unknown-critical-chunk"),
+                (Severity::Warning, "private-chunk"),
+                (Severity::Info, "ancillary-after-idat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_ancillary_after_idat() {
+        let png = testing_png(&["IDAT", "tEXt", "IEND"]);
+        let findings = png.lint();
+        assert_eq!(codes(&findings), [(Severity::Info, "ancillary-after-idat")]);
+        assert_eq!(
+            findings[0].to_string(),
+            "info: chunk #2 'tEXt' at offset 0x2D: ancillary chunk after the image data [ancillary-after-idat]"
+        );
+    }
+
+    #[test]
+    fn test_severity_order() {
+        assert!(Severity::Info < Severity::Warning);
+        assert!(Severity::Warning < Severity::Error);
+    }
+
+    #[test]
+    fn test_finding_to_json() {
+        let finding = Finding {
+            severity: Severity::Error,
+            code: "missing-iend",
+            message: "there is no \"IEND\" chunk".to_string(),
+        };
+        assert_eq!(
+            finding.to_json(),
+            r#"{"severity":"error","code":"missing-iend","message":"there is no \"IEND\" chunk"}"#
+        );
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\\b\n\u{1}"), r#""a\\b\n\u0001""#);
+    }
+}
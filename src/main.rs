@@ -1,40 +1,45 @@
 mod args;
 mod chunk;
 mod chunk_type;
+mod colorimetry;
 mod commands;
 mod crc32;
+mod dimensions;
 mod errors;
+mod exif;
+mod icc;
+mod ihdr;
+mod lint;
+mod palette;
 mod png;
+mod png_reader;
+mod png_writer;
+mod recovery;
+mod repair;
+mod samples;
+mod strip;
+#[cfg(test)]
+mod test_utils;
+mod text;
+mod time;
+mod typed_chunk;
+mod validate;
+mod zlib;
 
-pub(crate) type Result<T> = std::result::Result<T, errors::PngError>;
+use std::process::ExitCode;
+
+use clap::Parser;
 
-fn main() -> Result<()> {
-    //let ct = chunk_type::ChunkType::from_str("Ru$t");
-    //if let Err(e) = ct {
-    //    eprintln!("{e}");
-    //}
-    //let chunk_type = chunk_type::ChunkType::from_str("RuSt").unwrap();
-    //let data = "My life is like an eternal night...".as_bytes().to_vec();
+pub(crate) type Result<T> = std::result::Result<T, errors::PngError>;
 
-    //let crc = crc::Crc::<u32>::new(&CRC_32_ISO_HDLC)
-    //        .checksum(&[chunk_type.bytes().as_slice(), data.as_slice()].concat());
-    //println!("{crc}");
-    //Ok(())
-    let data_length: u32 = 35;
-    let chunk_type = "RuSt".as_bytes();
-    let message_bytes = "My life is like an eternal night...".as_bytes();
-    let crc: u32 = 2591807189;
+fn main() -> ExitCode {
+    let cli = args::Cli::parse();
 
-    let chunk_data: Vec<u8> = data_length
-        .to_be_bytes()
-        .iter()
-        .chain(chunk_type.iter())
-        .chain(message_bytes.iter())
-        .chain(crc.to_be_bytes().iter())
-        .copied()
-        .collect();
-    if let Err(e) = chunk::Chunk::try_from(chunk_data.as_ref()) {
-        println!("{e}");
+    match commands::run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
     }
-    Ok(())
 }
@@ -0,0 +1,747 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    errors::PngError,
+    ihdr::{ColorType, Ihdr},
+    png::Png,
+    text::{check_keyword, split_keyword, string_to_latin1},
+    typed_chunk::{be_u16, check_samples, DependentChunk, TypedChunk},
+};
+
+/// One color of a `Palette`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PaletteEntry {
+    pub(crate) red: u8,
+    pub(crate) green: u8,
+    pub(crate) blue: u8,
+}
+
+impl FromStr for PaletteEntry {
+    type Err = PngError;
+
+    /// Parses a color written as `RRGGBB` hex, e.g. `ff8000`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 6 || !s.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return Err(Palette::invalid(format_args!(
+                "color must be 6 hex digits, got '{s}'"
+            )));
+        }
+        let channel = |start: usize| u8::from_str_radix(&s[start..start + 2], 16).unwrap();
+        Ok(Self {
+            red: channel(0),
+            green: channel(2),
+            blue: channel(4),
+        })
+    }
+}
+
+/// The colors of an indexed image (`PLTE`). RGB and RGBA images
+/// may also carry one, as a suggestion for quantizing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Palette {
+    entries: Vec<PaletteEntry>,
+}
+
+impl Palette {
+    /// The most entries a palette can have.
+    pub(crate) const MAX_ENTRIES: usize = 256;
+
+    /// Creates a palette of 1 to 256 entries.
+    pub(crate) fn new(entries: Vec<PaletteEntry>) -> Result<Self, PngError> {
+        if entries.is_empty() || entries.len() > Self::MAX_ENTRIES {
+            return Err(Self::invalid(format_args!(
+                "must have 1 to {} entries, got {}",
+                Self::MAX_ENTRIES,
+                entries.len()
+            )));
+        }
+        Ok(Self { entries })
+    }
+
+    pub(crate) fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+
+    /// The entries can be changed, but not added or removed.
+    pub(crate) fn entries_mut(&mut self) -> &mut [PaletteEntry] {
+        &mut self.entries
+    }
+
+    /// Checks that the color type allows a palette, and that an indexed
+    /// image's bit depth can address every entry.
+    pub(crate) fn validate(&self, ihdr: &Ihdr) -> Result<(), PngError> {
+        match ihdr.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Err(Self::invalid(format_args!(
+                "not allowed for {} images",
+                ihdr.color_type
+            ))),
+            ColorType::Indexed if self.entries.len() > 1 << ihdr.bit_depth => {
+                Err(Self::invalid(format_args!(
+                    "{} entries can't be indexed with {} bits",
+                    self.entries.len(),
+                    ihdr.bit_depth
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Expands the palette to RGBA, taking the alpha of each entry
+    /// from `transparency` if the image has any.
+    pub(crate) fn to_rgba(&self, transparency: Option<&Transparency>) -> Vec<[u8; 4]> {
+        let alphas = match transparency {
+            Some(Transparency::Indexed(alphas)) => alphas.as_slice(),
+            _ => &[],
+        };
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let alpha = alphas.get(index).copied().unwrap_or(u8::MAX);
+                [entry.red, entry.green, entry.blue, alpha]
+            })
+            .collect()
+    }
+}
+
+impl TypedChunk for Palette {
+    const CHUNK_TYPE: [u8; 4] = *b"PLTE";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        if !data.len().is_multiple_of(3) {
+            return Err(Self::invalid(format_args!(
+                "length must be divisible by 3, got {}",
+                data.len()
+            )));
+        }
+        let entries = data
+            .chunks_exact(3)
+            .map(|rgb| PaletteEntry {
+                red: rgb[0],
+                green: rgb[1],
+                blue: rgb[2],
+            })
+            .collect();
+        Self::new(entries)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.entries
+            .iter()
+            .flat_map(|entry| [entry.red, entry.green, entry.blue])
+            .collect()
+    }
+}
+
+impl Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", entry_count(self.entries.len()))
+    }
+}
+
+fn entry_count(count: usize) -> String {
+    match count {
+        1 => "1 entry".to_string(),
+        count => format!("{count} entries"),
+    }
+}
+
+/// Simple transparency for images without an alpha channel (`tRNS`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Transparency {
+    /// The gray sample value that is fully transparent.
+    Gray(u16),
+    /// The RGB sample values that are fully transparent.
+    Rgb { red: u16, green: u16, blue: u16 },
+    /// The alpha of each palette entry, in order. Entries past
+    /// the end of the list are opaque.
+    Indexed(Vec<u8>),
+}
+
+impl Transparency {
+    /// Checks that an indexed image's alphas don't outnumber its palette.
+    pub(crate) fn validate_palette(&self, palette: &Palette) -> Result<(), PngError> {
+        match self {
+            Self::Indexed(alphas) if alphas.len() > palette.entries().len() => {
+                Err(Self::invalid(format_args!(
+                    "{} alpha values for {} palette entries",
+                    alphas.len(),
+                    palette.entries().len()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DependentChunk for Transparency {
+    const CHUNK_TYPE: [u8; 4] = *b"tRNS";
+
+    fn from_data(data: &[u8], ihdr: &Ihdr) -> Result<Self, PngError> {
+        let transparency = match ihdr.color_type {
+            ColorType::Grayscale => {
+                Self::check_length(data, 2)?;
+                Self::Gray(be_u16(data, 0))
+            }
+            ColorType::Rgb => {
+                Self::check_length(data, 6)?;
+                Self::Rgb {
+                    red: be_u16(data, 0),
+                    green: be_u16(data, 2),
+                    blue: be_u16(data, 4),
+                }
+            }
+            ColorType::Indexed => {
+                let max = 1 << ihdr.bit_depth;
+                if data.len() > max {
+                    return Err(Self::invalid(format_args!(
+                        "{} alpha values can't be indexed with {} bits",
+                        data.len(),
+                        ihdr.bit_depth
+                    )));
+                }
+                return Ok(Self::Indexed(data.to_vec()));
+            }
+            ColorType::GrayscaleAlpha | ColorType::Rgba => {
+                return Err(Self::invalid(format_args!(
+                    "not allowed for {} images, which have an alpha channel",
+                    ihdr.color_type
+                )))
+            }
+        };
+        check_samples::<Self>(data, ihdr.bit_depth)?;
+        Ok(transparency)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        match self {
+            Self::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Self::Rgb { red, green, blue } => [red, green, blue]
+                .into_iter()
+                .flat_map(|sample| sample.to_be_bytes())
+                .collect(),
+            Self::Indexed(alphas) => alphas.clone(),
+        }
+    }
+}
+
+impl Display for Transparency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gray(gray) => write!(f, "transparent gray {gray}"),
+            Self::Rgb { red, green, blue } => {
+                write!(f, "transparent color ({red}, {green}, {blue})")
+            }
+            Self::Indexed(alphas) => write!(f, "palette alpha for {}", entry_count(alphas.len())),
+        }
+    }
+}
+
+/// How often each palette entry is used in the image (`hIST`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Histogram {
+    frequencies: Vec<u16>,
+}
+
+impl Histogram {
+    /// Returns the approximate usage of each palette entry, in order.
+    #[allow(
+        dead_code,
+        reason = "no command reads the frequencies yet, print only needs Display"
+    )]
+    pub(crate) fn frequencies(&self) -> &[u16] {
+        &self.frequencies
+    }
+
+    /// Checks that there is exactly one frequency per palette entry.
+    pub(crate) fn validate_palette(&self, palette: &Palette) -> Result<(), PngError> {
+        if self.frequencies.len() != palette.entries().len() {
+            return Err(Self::invalid(format_args!(
+                "{} frequencies for {} palette entries",
+                self.frequencies.len(),
+                palette.entries().len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl TypedChunk for Histogram {
+    const CHUNK_TYPE: [u8; 4] = *b"hIST";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let entries = data.len() / 2;
+        if !data.len().is_multiple_of(2) || !(1..=Palette::MAX_ENTRIES).contains(&entries) {
+            return Err(Self::invalid(format_args!(
+                "length must be an even number from 2 to {}, got {}",
+                Palette::MAX_ENTRIES * 2,
+                data.len()
+            )));
+        }
+        let frequencies = (0..entries).map(|entry| be_u16(data, entry * 2)).collect();
+        Ok(Self { frequencies })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.frequencies
+            .iter()
+            .flat_map(|frequency| frequency.to_be_bytes())
+            .collect()
+    }
+}
+
+impl Display for Histogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let most_used = (0..self.frequencies.len())
+            .max_by_key(|&entry| (self.frequencies[entry], std::cmp::Reverse(entry)))
+            .unwrap_or_default();
+        write!(
+            f,
+            "frequencies for {}, entry {most_used} is the most used",
+            entry_count(self.frequencies.len())
+        )
+    }
+}
+
+/// One color of a `SuggestedPalette`. With a sample depth of 8,
+/// every sample is at most 255.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SuggestedPaletteEntry {
+    pub(crate) red: u16,
+    pub(crate) green: u16,
+    pub(crate) blue: u16,
+    pub(crate) alpha: u16,
+    /// Proportional to how often the color is used, or 0 if unknown.
+    pub(crate) frequency: u16,
+}
+
+/// A named palette for viewers that can't show the image's full
+/// range of colors (`sPLT`). An image may suggest several.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SuggestedPalette {
+    name: String,
+    sample_depth: u8,
+    entries: Vec<SuggestedPaletteEntry>,
+}
+
+impl SuggestedPalette {
+    /// Creates a suggested palette, checking the name against the keyword
+    /// rules and the samples against `sample_depth`, which is 8 or 16.
+    pub(crate) fn new(
+        name: &str,
+        sample_depth: u8,
+        entries: Vec<SuggestedPaletteEntry>,
+    ) -> Result<Self, PngError> {
+        check_keyword::<Self>(name)?;
+        match sample_depth {
+            8 => {
+                let too_big = entries.iter().any(|entry| {
+                    [entry.red, entry.green, entry.blue, entry.alpha]
+                        .iter()
+                        .any(|&sample| sample > u16::from(u8::MAX))
+                });
+                if too_big {
+                    return Err(Self::invalid("sample doesn't fit in 8 bits"));
+                }
+            }
+            16 => {}
+            _ => {
+                return Err(Self::invalid(format_args!(
+                    "sample depth must be 8 or 16, got {sample_depth}"
+                )))
+            }
+        }
+        Ok(Self {
+            name: name.to_string(),
+            sample_depth,
+            entries,
+        })
+    }
+
+    #[allow(dead_code, reason = "sPLT accessor, print only needs Display so far")]
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[allow(dead_code, reason = "sPLT accessor, print only needs Display so far")]
+    pub(crate) fn sample_depth(&self) -> u8 {
+        self.sample_depth
+    }
+
+    #[allow(dead_code, reason = "sPLT accessor, print only needs Display so far")]
+    pub(crate) fn entries(&self) -> &[SuggestedPaletteEntry] {
+        &self.entries
+    }
+}
+
+impl TypedChunk for SuggestedPalette {
+    const CHUNK_TYPE: [u8; 4] = *b"sPLT";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let (name, rest) = split_keyword::<Self>(data)?;
+        let (&sample_depth, entries) = rest
+            .split_first()
+            .ok_or_else(|| Self::invalid("missing sample depth"))?;
+        let entry_length = match sample_depth {
+            8 => 6,
+            16 => 10,
+            _ => {
+                return Err(Self::invalid(format_args!(
+                    "sample depth must be 8 or 16, got {sample_depth}"
+                )))
+            }
+        };
+        if !entries.len().is_multiple_of(entry_length) {
+            return Err(Self::invalid(format_args!(
+                "entries must be {entry_length} bytes each, got {} bytes",
+                entries.len()
+            )));
+        }
+        let entries = entries
+            .chunks_exact(entry_length)
+            .map(|entry| {
+                let sample = |index: usize| match sample_depth {
+                    8 => u16::from(entry[index]),
+                    _ => be_u16(entry, index * 2),
+                };
+                SuggestedPaletteEntry {
+                    red: sample(0),
+                    green: sample(1),
+                    blue: sample(2),
+                    alpha: sample(3),
+                    frequency: be_u16(entry, entry_length - 2),
+                }
+            })
+            .collect();
+        Self::new(&name, sample_depth, entries)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = string_to_latin1(&self.name).expect("checked by new");
+        data.extend([0, self.sample_depth]);
+        for entry in &self.entries {
+            for sample in [entry.red, entry.green, entry.blue, entry.alpha] {
+                match self.sample_depth {
+                    8 => data.push(sample as u8),
+                    _ => data.extend(sample.to_be_bytes()),
+                }
+            }
+            data.extend(entry.frequency.to_be_bytes());
+        }
+        data
+    }
+}
+
+impl Display for SuggestedPalette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}, {}-bit samples",
+            self.name,
+            entry_count(self.entries.len()),
+            self.sample_depth
+        )
+    }
+}
+
+impl Png {
+    /// Parses the palette, if there is one.
+    pub(crate) fn palette(&self) -> Option<Result<Palette, PngError>> {
+        self.find::<Palette>()
+    }
+
+    /// Parses the tRNS chunk against the image header and palette,
+    /// if there is one.
+    pub(crate) fn transparency(&self) -> Option<Result<Transparency, PngError>> {
+        let transparency = self.find_dependent::<Transparency>()?;
+        Some(transparency.and_then(|transparency| {
+            if let Some(palette) = self.palette() {
+                transparency.validate_palette(&palette?)?;
+            }
+            Ok(transparency)
+        }))
+    }
+
+    /// Replaces the palette in place, or inserts it before the chunks
+    /// that must follow it if there was none. Fails if the image header
+    /// doesn't allow the palette.
+    pub(crate) fn set_palette(&mut self, palette: Palette) -> Result<(), PngError> {
+        palette.validate(&self.ihdr()?)?;
+        let index = self.position_before(&[*b"PLTE", *b"tRNS", *b"bKGD", *b"hIST"]);
+        self.remove_chunks_where(|chunk| chunk.chunk_type().bytes() == Palette::CHUNK_TYPE);
+        self.insert_chunk(index, palette.to_chunk());
+        Ok(())
+    }
+
+    /// Replaces the tRNS chunk in place, or inserts it before the chunks
+    /// that must follow it if there was none. Fails if it doesn't fit the
+    /// image header or palette.
+    pub(crate) fn set_transparency(&mut self, transparency: Transparency) -> Result<(), PngError> {
+        let chunk = transparency.to_chunk();
+        let ihdr = self.ihdr()?;
+        // Parsing the chunk back checks it against the header, and
+        // only gives the same value if the layout fits the color type.
+        if Transparency::from_chunk(&chunk, &ihdr)? != transparency {
+            return Err(Transparency::invalid(format_args!(
+                "{transparency} isn't allowed for {} images",
+                ihdr.color_type
+            )));
+        }
+        if let Some(palette) = self.palette() {
+            transparency.validate_palette(&palette?)?;
+        }
+        let index = self.position_before(&[*b"tRNS", *b"bKGD", *b"hIST"]);
+        self.remove_chunks_where(|chunk| chunk.chunk_type().bytes() == Transparency::CHUNK_TYPE);
+        self.insert_chunk(index, chunk);
+        Ok(())
+    }
+
+    /// The index of the first chunk of one of the given types, IDAT or IEND.
+    fn position_before(&self, chunk_types: &[[u8; 4]]) -> usize {
+        self.chunks()
+            .iter()
+            .position(|chunk| {
+                let bytes = chunk.chunk_type().bytes();
+                chunk_types.contains(&bytes) || matches!(&bytes, b"IDAT" | b"IEND")
+            })
+            .unwrap_or(self.chunks().len())
+    }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use super::*;
+    use crate::test_utils::{chunk_with_data, ihdr};
+
+    #[test]
+    fn test_palette_round_trip() {
+        let data = [255, 0, 0, 0, 255, 0];
+        let palette = Palette::from_data(&data).unwrap();
+        assert_eq!(
+            palette.entries()[1],
+            PaletteEntry {
+                red: 0,
+                green: 255,
+                blue: 0
+            }
+        );
+        assert_eq!(palette.to_data(), data);
+        assert_eq!(palette.to_string(), "2 entries");
+    }
+
+    #[test]
+    fn test_palette_length_checks() {
+        assert!(Palette::from_data(&[]).is_err());
+        assert!(Palette::from_data(&[0; 4]).is_err());
+        assert!(Palette::from_data(&[0; 768]).is_ok());
+        assert!(Palette::from_data(&[0; 771]).is_err());
+    }
+
+    #[test]
+    fn test_palette_validate() {
+        let palette = Palette::from_data(&[0; 3 * 5]).unwrap();
+        assert!(palette.validate(&ihdr(ColorType::Indexed, 4)).is_ok());
+        assert!(palette.validate(&ihdr(ColorType::Indexed, 2)).is_err());
+        assert!(palette.validate(&ihdr(ColorType::Rgb, 8)).is_ok());
+        assert!(palette.validate(&ihdr(ColorType::Grayscale, 8)).is_err());
+    }
+
+    #[test]
+    fn test_transparency_layouts() {
+        let gray = Transparency::from_data(&[0, 3], &ihdr(ColorType::Grayscale, 2)).unwrap();
+        assert_eq!(gray, Transparency::Gray(3));
+        assert_eq!(gray.to_string(), "transparent gray 3");
+
+        let data = [0x01, 0x00, 0, 0, 0xFF, 0xFF];
+        let rgb = Transparency::from_data(&data, &ihdr(ColorType::Rgb, 16)).unwrap();
+        assert_eq!(
+            rgb,
+            Transparency::Rgb {
+                red: 256,
+                green: 0,
+                blue: u16::MAX
+            }
+        );
+        assert_eq!(rgb.to_data(), data);
+
+        let indexed = Transparency::from_data(&[0, 128], &ihdr(ColorType::Indexed, 8)).unwrap();
+        assert_eq!(indexed.to_string(), "palette alpha for 2 entries");
+    }
+
+    #[test]
+    fn test_transparency_checks_against_ihdr() {
+        // The sample doesn't fit in 2 bits.
+        assert!(Transparency::from_data(&[0, 4], &ihdr(ColorType::Grayscale, 2)).is_err());
+        assert!(Transparency::from_data(&[0; 4], &ihdr(ColorType::Grayscale, 8)).is_err());
+        assert!(Transparency::from_data(&[0; 2], &ihdr(ColorType::Rgb, 8)).is_err());
+        assert!(Transparency::from_data(&[0; 3], &ihdr(ColorType::Indexed, 1)).is_err());
+        assert!(Transparency::from_data(&[0; 2], &ihdr(ColorType::GrayscaleAlpha, 8)).is_err());
+    }
+
+    #[test]
+    fn test_palette_to_rgba() {
+        let palette = Palette::from_data(&[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(palette.to_rgba(None), [[1, 2, 3, 255], [4, 5, 6, 255]]);
+        let transparency = Transparency::Indexed(vec![0]);
+        assert_eq!(
+            palette.to_rgba(Some(&transparency)),
+            [[1, 2, 3, 0], [4, 5, 6, 255]]
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::from_data(&[0, 5, 1, 0, 1, 0]).unwrap();
+        assert_eq!(histogram.frequencies(), [5, 256, 256]);
+        assert_eq!(histogram.to_data(), [0, 5, 1, 0, 1, 0]);
+        assert_eq!(
+            histogram.to_string(),
+            "frequencies for 3 entries, entry 1 is the most used"
+        );
+        assert_eq!(
+            Histogram::from_data(&[0, 1]).unwrap().to_string(),
+            "frequencies for 1 entry, entry 0 is the most used"
+        );
+        assert!(Histogram::from_data(&[]).is_err());
+        assert!(Histogram::from_data(&[0; 3]).is_err());
+        assert!(Histogram::from_data(&[0; 514]).is_err());
+    }
+
+    #[test]
+    fn test_histogram_validate_palette() {
+        let histogram = Histogram::from_data(&[0; 4]).unwrap();
+        assert!(histogram
+            .validate_palette(&Palette::from_data(&[0; 6]).unwrap())
+            .is_ok());
+        assert!(histogram
+            .validate_palette(&Palette::from_data(&[0; 9]).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_suggested_palette_round_trip() {
+        let mut data = b"web\0\x08".to_vec();
+        data.extend([255, 0, 0, 255, 0, 9]);
+        let palette = SuggestedPalette::from_data(&data).unwrap();
+        assert_eq!(palette.name(), "web");
+        assert_eq!(palette.sample_depth(), 8);
+        assert_eq!(
+            palette.entries(),
+            [SuggestedPaletteEntry {
+                red: 255,
+                green: 0,
+                blue: 0,
+                alpha: 255,
+                frequency: 9
+            }]
+        );
+        assert_eq!(palette.to_data(), data);
+        assert_eq!(palette.to_string(), "web: 1 entry, 8-bit samples");
+
+        let mut data = b"deep\0\x10".to_vec();
+        data.extend([1, 0, 0, 0, 0, 0, 255, 255, 0, 1]);
+        let palette = SuggestedPalette::from_data(&data).unwrap();
+        assert_eq!(palette.entries()[0].red, 256);
+        assert_eq!(palette.entries()[0].alpha, u16::MAX);
+        assert_eq!(palette.to_data(), data);
+    }
+
+    #[test]
+    fn test_suggested_palette_checks() {
+        assert!(SuggestedPalette::from_data(b"web").is_err());
+        assert!(SuggestedPalette::from_data(b"web\0").is_err());
+        assert!(SuggestedPalette::from_data(b"web\0\x04").is_err());
+        assert!(SuggestedPalette::from_data(b"web\0\x08\0\0\0").is_err());
+        assert!(SuggestedPalette::from_data(b" web\0\x08").is_err());
+        let entry = SuggestedPaletteEntry {
+            red: 256,
+            green: 0,
+            blue: 0,
+            alpha: 0,
+            frequency: 0,
+        };
+        assert!(SuggestedPalette::new("web", 8, vec![entry]).is_err());
+        assert!(SuggestedPalette::new("web", 16, vec![entry]).is_ok());
+    }
+
+    #[test]
+    fn test_png_transparency() {
+        let png = Png::from_chunks(vec![
+            ihdr(ColorType::Indexed, 8).to_chunk(),
+            chunk_with_data("PLTE", &[0; 3]),
+            chunk_with_data("tRNS", &[0, 0]),
+        ]);
+        assert_eq!(png.palette().unwrap().unwrap().entries().len(), 1);
+        assert!(png.transparency().unwrap().is_err());
+
+        let png = Png::from_chunks(vec![chunk_with_data("tRNS", &[0, 0])]);
+        assert!(matches!(
+            png.transparency(),
+            Some(Err(PngError::IhdrNotFirst))
+        ));
+    }
+
+    #[test]
+    fn test_png_set_palette() {
+        let mut png = Png::from_chunks(vec![
+            ihdr(ColorType::Indexed, 8).to_chunk(),
+            chunk_with_data("gAMA", &[0; 4]),
+            chunk_with_data("bKGD", &[0]),
+            chunk_with_data("IDAT", &[]),
+            chunk_with_data("IEND", &[]),
+        ]);
+        let mut palette = Palette::from_data(&[0; 6]).unwrap();
+        png.set_palette(palette.clone()).unwrap();
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "PLTE");
+
+        palette.entries_mut()[0].red = 7;
+        png.set_palette(palette.clone()).unwrap();
+        assert_eq!(png.chunks().len(), 6);
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "PLTE");
+        assert_eq!(png.palette().unwrap().unwrap(), palette);
+
+        let mut png = Png::from_chunks(vec![ihdr(ColorType::Grayscale, 8).to_chunk()]);
+        assert!(png.set_palette(palette).is_err());
+        assert_eq!(png.chunks().len(), 1);
+    }
+
+    #[test]
+    fn test_png_set_transparency() {
+        let mut png = Png::from_chunks(vec![
+            ihdr(ColorType::Indexed, 8).to_chunk(),
+            chunk_with_data("PLTE", &[0; 6]),
+            chunk_with_data("IDAT", &[]),
+        ]);
+        png.set_transparency(Transparency::Indexed(vec![0, 128]))
+            .unwrap();
+        assert_eq!(png.chunks()[2].chunk_type().to_string(), "tRNS");
+        png.set_transparency(Transparency::Indexed(vec![64]))
+            .unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(
+            png.transparency().unwrap().unwrap(),
+            Transparency::Indexed(vec![64])
+        );
+
+        // More alphas than palette entries, and a layout for another color type.
+        assert!(png
+            .set_transparency(Transparency::Indexed(vec![0; 3]))
+            .is_err());
+        assert!(png.set_transparency(Transparency::Gray(0)).is_err());
+    }
+
+    #[test]
+    fn test_palette_entry_from_str() {
+        assert_eq!(
+            PaletteEntry::from_str("Ff8000").unwrap(),
+            PaletteEntry {
+                red: 255,
+                green: 128,
+                blue: 0
+            }
+        );
+        assert!(PaletteEntry::from_str("ff800").is_err());
+        assert!(PaletteEntry::from_str("+f8000").is_err());
+        assert!(PaletteEntry::from_str("ff8000ff").is_err());
+    }
+}
@@ -1,6 +1,15 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
 use crate::{
     chunk::Chunk,
-    errors::{self, Expectations, PngError},
+    chunk_type::ChunkType,
+    errors::{self, ChunkContext, PngError},
+    png_writer::PngWriter,
 };
 
 /// A PNG file.
@@ -16,8 +25,8 @@ pub(crate) struct Png {
 }
 
 impl Png {
-    const PNG_FILE_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
-    const MIN_CHUNK_LENGTH: usize = 12;
+    pub(crate) const PNG_FILE_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub(crate) const MIN_CHUNK_LENGTH: usize = 12;
 
     /// Creates a new PNG from some chunks.
     pub(crate) fn from_chunks(chunks: Vec<Chunk>) -> Self {
@@ -32,7 +41,17 @@ impl Png {
         &self.chunks
     }
 
+    /// Returns the byte offset each chunk would have in the written file.
+    pub(crate) fn chunk_offsets(&self) -> impl Iterator<Item = usize> + '_ {
+        self.chunks.iter().scan(8, |offset, chunk| {
+            let chunk_offset = *offset;
+            *offset += Self::MIN_CHUNK_LENGTH + chunk.data().len();
+            Some(chunk_offset)
+        })
+    }
+
     /// Returns the PNG file signature as a slice of eight bytes.
+    #[allow(dead_code, reason = "PngWriter always writes the standard signature")]
     pub(crate) fn signature(&self) -> &[u8; 8] {
         &self.signature
     }
@@ -53,6 +72,37 @@ impl Png {
         self.chunks.push(chunk);
     }
 
+    /// Inserts a chunk at `index`, shifting the chunks after it.
+    pub(crate) fn insert_chunk(&mut self, index: usize, chunk: Chunk) {
+        self.chunks.insert(index, chunk);
+    }
+
+    /// Inserts a chunk right before `IEND`, or at the end
+    /// if there is no `IEND`.
+    pub(crate) fn insert_before_iend(&mut self, chunk: Chunk) {
+        let iend = self
+            .chunks
+            .iter()
+            .position(|chunk| chunk.chunk_type().bytes() == *b"IEND");
+        match iend {
+            Some(index) => self.insert_chunk(index, chunk),
+            None => self.append_chunk(chunk),
+        }
+    }
+
+    /// Removes every chunk for which `predicate` returns true,
+    /// returning them in file order.
+    pub(crate) fn remove_chunks_where(
+        &mut self,
+        mut predicate: impl FnMut(&Chunk) -> bool,
+    ) -> Vec<Chunk> {
+        let (removed, kept) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| predicate(chunk));
+        self.chunks = kept;
+        removed
+    }
+
     /// Removes a chunk from the PNG.
     pub(crate) fn remove_chunk(&mut self, chunk_type: &str) -> Result<Chunk, PngError> {
         let query = self.search_chunk(chunk_type);
@@ -63,36 +113,153 @@ impl Png {
         let removed_chunk = self.chunks.remove(index);
         Ok(removed_chunk)
     }
+
+    /// Removes every chunk of the given type, returning them in file order.
+    /// Fails if `chunk_type` isn't a valid chunk type.
+    pub(crate) fn remove_chunks(&mut self, chunk_type: &str) -> Result<Vec<Chunk>, PngError> {
+        let chunk_type = ChunkType::from_str(chunk_type)?;
+        let removed = self.remove_chunks_where(|chunk| chunk.chunk_type() == &chunk_type);
+        if removed.is_empty() {
+            return Err(PngError::ChunkNotFound);
+        }
+        Ok(removed)
+    }
+
+    /// Returns the PNG as a vector of bytes:
+    /// the signature followed by every chunk.
+    #[cfg(test)]
+    pub(crate) fn as_bytes(&self) -> Vec<u8> {
+        self.write_to(Vec::new())
+            .expect("writing to a Vec<u8> never fails")
+    }
+
+    /// Writes the signature followed by every chunk to `writer`
+    /// through a `PngWriter`, and returns the writer.
+    pub(crate) fn write_to<W: Write>(&self, writer: W) -> Result<W, PngError> {
+        let mut png_writer = PngWriter::new(writer)?;
+        for chunk in self.chunks() {
+            png_writer.write_chunk(chunk)?;
+        }
+        png_writer.finish()
+    }
+
+    /// Saves the PNG to `path`.
+    ///
+    /// The bytes are first written to a temporary file next to `path`,
+    /// which is then renamed over it, so `path` either keeps its old
+    /// contents or gets the complete new ones.
+    pub(crate) fn save(&self, path: &Path) -> Result<(), PngError> {
+        let temp_path = Self::temp_path_for(path);
+        let result = self
+            .write_temp_file(&temp_path)
+            .and_then(|()| fs::rename(&temp_path, path).map_err(PngError::Write));
+
+        result.map_err(|error| {
+            // Best effort: the original error is more useful than a failed cleanup.
+            let _ = fs::remove_file(&temp_path);
+            match error {
+                PngError::Write(source) => PngError::FileAccess {
+                    path: path.to_path_buf(),
+                    source,
+                },
+                error => error,
+            }
+        })
+    }
+
+    fn write_temp_file(&self, temp_path: &Path) -> Result<(), PngError> {
+        let file = File::create(temp_path).map_err(PngError::Write)?;
+        let file = self
+            .write_to(BufWriter::new(file))?
+            .into_inner()
+            .map_err(|e| PngError::Write(e.into_error()))?;
+        file.sync_all().map_err(PngError::Write)
+    }
+
+    /// A hidden sibling of `path`, so the rename never crosses filesystems.
+    fn temp_path_for(path: &Path) -> PathBuf {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()))
+    }
+
+    /// Checks the PNG file signature at the start of `value`.
+    pub(crate) fn signature_of(value: &[u8]) -> Result<[u8; 8], PngError> {
+        let signature: [u8; 8] = Self::bytes_at(value, 0)?;
+        if !signature.eq(&Self::PNG_FILE_SIGNATURE) {
+            return Err(PngError::InvalidPngSignature);
+        }
+        Ok(signature)
+    }
+
+    /// Returns the bytes of the chunk starting at `starting_cursor`,
+    /// from its length field up to and including its CRC.
+    ///
+    /// Only the length field is checked, the chunk itself isn't parsed.
+    pub(crate) fn raw_chunk_at(value: &[u8], starting_cursor: usize) -> Result<&[u8], PngError> {
+        // Get the `length` field of the `Chunk`.
+        let chunk_length_bytes: [u8; 4] = Self::bytes_at(value, starting_cursor)?;
+        let chunk_length = u32::from_be_bytes(chunk_length_bytes);
+        if chunk_length > Chunk::MAX_LENGTH {
+            return Err(PngError::ChunkLengthTooLarge(chunk_length));
+        }
+
+        // We can now get upto which byte the current chunk exists.
+        let end_cursor =
+            starting_cursor.saturating_add(Self::MIN_CHUNK_LENGTH + chunk_length as usize);
+        if end_cursor > value.len() {
+            return Err(PngError::ChunkLengthExceedsFile {
+                offset: starting_cursor,
+                length: chunk_length,
+                available: value.len() - starting_cursor,
+            });
+        }
+
+        // The chunk exists from `starting_cursor` upto `end_cursor-1`
+        Ok(&value[starting_cursor..end_cursor])
+    }
+
+    /// Copies `N` bytes starting at `offset` out of `value`, or reports
+    /// how many bytes were needed if `value` ends too early.
+    pub(crate) fn bytes_at<const N: usize>(
+        value: &[u8],
+        offset: usize,
+    ) -> Result<[u8; N], PngError> {
+        value
+            .get(offset..)
+            .and_then(|rest| rest.get(..N))
+            .map(|bytes| bytes.try_into().expect("slice has exactly N bytes"))
+            .ok_or(PngError::UnexpectedEof { offset, needed: N })
+    }
 }
 
 impl TryFrom<&[u8]> for Png {
     type Error = PngError;
+
+    /// Parses a PNG from a slice of bytes.
+    ///
+    /// Every read is bounds-checked, so truncated or hostile input
+    /// produces a `PngError` instead of a panic.
     fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
         // First take the signature.
-        let signature: [u8; 8] = value[..8].try_into()?;
-        if !signature.eq(&Self::PNG_FILE_SIGNATURE) {
-            return Err(Self::Error::InvalidPngSignature);
-        }
+        let signature = Self::signature_of(value)?;
         // The chunks start from the 8th byte of `value` after
         // taking into account the signature.
         let mut starting_cursor = 8;
         let mut chunks = Vec::<Chunk>::new();
-        let mut end_cursor: usize;
 
         // While the end cursor doesn't reach the length of the slice.
         while starting_cursor < value.len() {
-            // Get the `length` field of the `Chunk`.
-            let chunk_length_bytes: [u8; 4] =
-                value[starting_cursor..starting_cursor + 4].try_into()?;
-            let chunk_length = u32::from_be_bytes(chunk_length_bytes) as usize;
-
-            // We can now get upto which byte the current chunk exists.
-            end_cursor = Self::MIN_CHUNK_LENGTH + chunk_length + starting_cursor;
-
-            // The chunk exists from `starting_cursor` upto `end_cursor-1`
-            let chunk = Chunk::try_from(&value[starting_cursor..end_cursor])?;
+            let in_chunk = |error: PngError| {
+                let chunk_type = Self::bytes_at(value, starting_cursor + 4).ok();
+                error.in_chunk(ChunkContext::new(chunks.len(), chunk_type, starting_cursor))
+            };
+            let raw_chunk = Self::raw_chunk_at(value, starting_cursor).map_err(in_chunk)?;
+            let chunk = Chunk::try_from(raw_chunk).map_err(in_chunk)?;
             chunks.push(chunk);
-            starting_cursor = end_cursor;
+            starting_cursor += raw_chunk.len();
         }
 
         Ok(Self { signature, chunks })
@@ -105,18 +272,9 @@ mod pngtests {
 
     use super::*;
     use crate::errors::PngError;
+    use crate::test_utils::{get_testing_chunks, get_testing_png_bytes};
     use crate::{chunk::Chunk, chunk_type::ChunkType};
 
-    fn get_testing_chunks() -> Vec<Chunk> {
-        let chunks = vec![
-            get_chunk_from_strings("RuSt", "I don't know what I'm doing").unwrap(),
-            get_chunk_from_strings("TeAr", "Yes I'm crying").unwrap(),
-            get_chunk_from_strings("RaGe", "Nooooooo").unwrap(),
-        ];
-
-        chunks
-    }
-
     fn get_chunk_from_strings(chunk_type: &str, data: &str) -> Result<Chunk, PngError> {
         let chunk_type = ChunkType::from_str(chunk_type)?;
         let chunk_data = data.as_bytes().to_vec();
@@ -130,6 +288,14 @@ mod pngtests {
         Png::from_chunks(chunks)
     }
 
+    #[test]
+    fn test_chunk_offsets() {
+        let png = get_png_from_chunks();
+        let offsets: Vec<usize> = png.chunk_offsets().collect();
+        // Each chunk is 12 bytes plus its data.
+        assert_eq!(offsets, [8, 8 + 12 + 27, 8 + 12 + 27 + 12 + 14]);
+    }
+
     #[test]
     fn test_chunks_of_png() {
         let png = get_png_from_chunks();
@@ -235,4 +401,190 @@ mod pngtests {
         assert!(removed.is_err());
         assert!(matches!(removed, Err(PngError::ChunkNotFound)));
     }
+
+    #[test]
+    fn test_remove_chunks() {
+        let mut png = Png::from_chunks(get_testing_chunks());
+        png.append_chunk(get_chunk_from_strings("RuSt", "again").unwrap());
+        let removed = png.remove_chunks("RuSt").unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(png.search_chunk("RuSt").is_none());
+        assert_eq!(png.chunks().len(), 2);
+        assert!(matches!(
+            png.remove_chunks("RuSt"),
+            Err(PngError::ChunkNotFound)
+        ));
+        assert!(png.remove_chunks("Ru5t").is_err());
+        assert_eq!(png.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_insert_before_iend() {
+        let mut png = Png::from_chunks(get_testing_chunks());
+        png.insert_before_iend(get_chunk_from_strings("CuTe", "").unwrap());
+        assert_eq!(png.search_chunk("CuTe").unwrap().0, 3);
+
+        png.append_chunk(get_chunk_from_strings("IEND", "").unwrap());
+        png.insert_before_iend(get_chunk_from_strings("TiNy", "").unwrap());
+        assert_eq!(png.search_chunk("TiNy").unwrap().0, 4);
+        assert_eq!(png.search_chunk("IEND").unwrap().0, 5);
+    }
+
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = Png::from_chunks(get_testing_chunks());
+        png.insert_chunk(1, get_chunk_from_strings("CuTe", "").unwrap());
+        assert_eq!(png.search_chunk("CuTe").unwrap().0, 1);
+        assert_eq!(png.chunks().len(), 4);
+    }
+
+    #[test]
+    fn test_remove_chunks_where() {
+        let mut png = Png::from_chunks(get_testing_chunks());
+        let removed = png.remove_chunks_where(|chunk| chunk.chunk_type().bytes()[0] == b'R');
+        let removed: Vec<String> = removed
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect();
+        assert_eq!(removed, ["RuSt", "RaGe"]);
+        assert_eq!(png.chunks().len(), 1);
+    }
+
+    #[test]
+    fn test_png_as_bytes_round_trip() {
+        let png = Png::from_chunks(get_testing_chunks());
+        let bytes = png.as_bytes();
+        assert!(bytes.starts_with(&Png::PNG_FILE_SIGNATURE));
+
+        let parsed = Png::try_from(bytes.as_ref()).unwrap();
+        assert_eq!(parsed.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_png_write_to() {
+        let png = Png::from_chunks(get_testing_chunks());
+        let mut written = Vec::new();
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, png.as_bytes());
+    }
+
+    #[test]
+    fn test_png_save() {
+        let path =
+            std::env::temp_dir().join(format!("hushpong-png-save-{}.png", std::process::id()));
+        std::fs::write(&path, b"old contents").unwrap();
+
+        let png = Png::from_chunks(get_testing_chunks());
+        png.save(&path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), png.as_bytes());
+        assert!(!Png::temp_path_for(&path).exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_png_save_to_missing_directory() {
+        let png = Png::from_chunks(get_testing_chunks());
+        let result = png.save(Path::new("/nonexistent/hushpong.png"));
+        assert!(matches!(result, Err(PngError::FileAccess { .. })));
+    }
+
+    #[test]
+    fn test_truncated_png_never_panics() {
+        let bytes = get_testing_png_bytes();
+        // Cutting the file exactly between two chunks still leaves a valid PNG.
+        let mut boundaries = vec![8];
+        for chunk in get_testing_chunks() {
+            boundaries.push(boundaries.last().unwrap() + chunk.as_bytes().len());
+        }
+
+        for end in 0..=bytes.len() {
+            let png = Png::try_from(&bytes[..end]);
+            assert_eq!(
+                png.is_ok(),
+                boundaries.contains(&end),
+                "prefix of {end} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn test_truncated_signature() {
+        let png = Png::try_from([137, 80, 78].as_ref());
+        assert!(matches!(
+            png,
+            Err(PngError::UnexpectedEof {
+                offset: 0,
+                needed: 8
+            })
+        ));
+    }
+
+    #[test]
+    fn test_truncated_length_field() {
+        let mut bytes = Png::PNG_FILE_SIGNATURE.to_vec();
+        bytes.extend_from_slice(&[0, 0]);
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(
+            error.root(),
+            PngError::UnexpectedEof {
+                offset: 8,
+                needed: 4
+            }
+        ));
+    }
+
+    #[test]
+    fn test_chunk_length_exceeds_file() {
+        let mut bytes = get_testing_png_bytes();
+        // Bump the first chunk's length field past the end of the file.
+        bytes[8..12].copy_from_slice(&1000u32.to_be_bytes());
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(
+            error.root(),
+            PngError::ChunkLengthExceedsFile {
+                offset: 8,
+                length: 1000,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_chunk_length_too_large() {
+        let mut bytes = get_testing_png_bytes();
+        bytes[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(
+            error.root(),
+            PngError::ChunkLengthTooLarge(u32::MAX)
+        ));
+    }
+
+    #[test]
+    fn test_error_names_the_failing_chunk() {
+        let mut bytes = get_testing_png_bytes();
+        // Corrupt the last byte of the second chunk's CRC.
+        let first_chunk_end = 8 + get_testing_chunks()[0].as_bytes().len();
+        let second_chunk_end = first_chunk_end + get_testing_chunks()[1].as_bytes().len();
+        bytes[second_chunk_end - 1] ^= 0xFF;
+
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(error.root(), PngError::InvalidCrc(_)));
+        assert!(error.to_string().ends_with(&format!(
+            "in chunk #1 'TeAr' at offset {first_chunk_end:#X}"
+        )));
+    }
+
+    #[test]
+    fn test_error_names_chunk_with_invalid_type() {
+        let mut bytes = get_testing_png_bytes();
+        bytes[12] = b'$';
+
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(matches!(error.root(), PngError::InvalidByte));
+        assert!(error
+            .to_string()
+            .ends_with("in chunk #0 '$uSt' at offset 0x8"));
+    }
 }
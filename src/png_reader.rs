@@ -0,0 +1,150 @@
+use std::io::{ErrorKind, Read};
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    errors::{ChunkContext, PngError},
+    png::Png,
+};
+
+/// Reads a PNG file one chunk at a time.
+///
+/// Unlike `Png::try_from`, only the chunk currently being read is kept in
+/// memory, so a message chunk can be found in a large file without
+/// loading the whole image.
+/// Iteration stops after the first error.
+pub(crate) struct PngReader<R: Read> {
+    reader: R,
+    /// The index of the next chunk.
+    index: usize,
+    /// The byte offset of the next chunk in the file.
+    offset: usize,
+    done: bool,
+}
+
+impl<R: Read> PngReader<R> {
+    /// Creates a new reader after checking the PNG file signature.
+    pub(crate) fn new(mut reader: R) -> Result<Self, PngError> {
+        let mut signature: [u8; 8] = [0; 8];
+        reader.read_exact(&mut signature)?;
+        if !signature.eq(&Png::PNG_FILE_SIGNATURE) {
+            return Err(PngError::InvalidPngSignature);
+        }
+        Ok(Self {
+            reader,
+            index: 0,
+            offset: signature.len(),
+            done: false,
+        })
+    }
+
+    /// Reads the next chunk's length field.
+    ///
+    /// Returns `None` if the reader ends cleanly before it,
+    /// which marks the end of the file.
+    fn read_length(&mut self) -> Result<Option<u32>, PngError> {
+        let mut buffer: [u8; 4] = [0; 4];
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match self.reader.read(&mut buffer[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(Some(u32::from_be_bytes(buffer)))
+    }
+
+    /// Reads the next chunk, or `None` at the end of the file.
+    ///
+    /// The raw chunk type bytes are stored in `chunk_type` as soon as they
+    /// are read, so errors can name the chunk even if the type is invalid.
+    fn read_chunk(&mut self, chunk_type: &mut Option<[u8; 4]>) -> Result<Option<Chunk>, PngError> {
+        let Some(length) = self.read_length()? else {
+            return Ok(None);
+        };
+        let mut buffer: [u8; 4] = [0; 4];
+        self.reader.read_exact(&mut buffer)?;
+        *chunk_type = Some(buffer);
+
+        Chunk::read_body(&mut self.reader, length, ChunkType::try_from(buffer)?).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for PngReader<R> {
+    type Item = Result<Chunk, PngError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut chunk_type = None;
+        match self.read_chunk(&mut chunk_type) {
+            Ok(Some(chunk)) => {
+                self.index += 1;
+                self.offset += Png::MIN_CHUNK_LENGTH + chunk.length() as usize;
+                Some(Ok(chunk))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                let context = ChunkContext::new(self.index, chunk_type, self.offset);
+                Some(Err(error.in_chunk(context)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod png_reader_tests {
+    use super::*;
+    use crate::test_utils::get_testing_png_bytes;
+
+    #[test]
+    fn test_reader_yields_every_chunk() {
+        let bytes = get_testing_png_bytes();
+        let reader = PngReader::new(bytes.as_slice()).unwrap();
+        let chunk_types: Vec<String> = reader
+            .map(|chunk| chunk.unwrap().chunk_type().to_string())
+            .collect();
+        assert_eq!(chunk_types, ["RuSt", "TeAr", "RaGe"]);
+    }
+
+    #[test]
+    fn test_reader_invalid_signature() {
+        let mut bytes = get_testing_png_bytes();
+        bytes[0] = 130;
+        let reader = PngReader::new(bytes.as_slice());
+        assert!(matches!(reader, Err(PngError::InvalidPngSignature)));
+    }
+
+    #[test]
+    fn test_reader_truncated_chunk() {
+        let bytes = get_testing_png_bytes();
+        let truncated = &bytes[..bytes.len() - 2];
+        let results: Vec<_> = PngReader::new(truncated).unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[..2].iter().all(Result::is_ok));
+        let error = results[2].as_ref().unwrap_err();
+        assert!(matches!(error.root(), PngError::ReadFromByteSlice(_)));
+        assert!(error
+            .to_string()
+            .ends_with("in chunk #2 'RaGe' at offset 0x49"));
+    }
+
+    #[test]
+    fn test_reader_stops_after_error() {
+        let mut bytes = get_testing_png_bytes();
+        // Corrupt the first chunk's CRC.
+        bytes[8 + 4 + 4 + 4] ^= 0xFF;
+        let mut reader = PngReader::new(bytes.as_slice()).unwrap();
+        let error = reader.next().unwrap().unwrap_err();
+        assert!(matches!(error.root(), PngError::InvalidCrc(_)));
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,202 @@
+use std::io::Write;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, crc32::Crc32, errors::PngError, png::Png};
+
+/// Writes a PNG file one chunk at a time.
+///
+/// A chunk can either be written whole with `write_chunk`, or started with
+/// `begin_chunk` and fed in pieces through `write_data`. In the second case
+/// the CRC is computed as the data goes out, so the chunk's data never has
+/// to be held in memory as a whole.
+pub(crate) struct PngWriter<W: Write> {
+    writer: W,
+    open_chunk: Option<OpenChunk>,
+}
+
+/// A chunk whose header has been written but whose CRC hasn't.
+struct OpenChunk {
+    declared: u32,
+    written: u64,
+    crc: Crc32,
+}
+
+impl<W: Write> PngWriter<W> {
+    /// Creates a new writer and writes the PNG file signature.
+    pub(crate) fn new(mut writer: W) -> Result<Self, PngError> {
+        writer
+            .write_all(&Png::PNG_FILE_SIGNATURE)
+            .map_err(PngError::Write)?;
+        Ok(Self {
+            writer,
+            open_chunk: None,
+        })
+    }
+
+    /// Writes a complete `Chunk`, as a single piece of data.
+    pub(crate) fn write_chunk(&mut self, chunk: &Chunk) -> Result<(), PngError> {
+        self.begin_chunk(chunk.chunk_type(), chunk.length())?;
+        self.write_data(chunk.data())?;
+        self.finish_chunk()
+    }
+
+    /// Writes the length and type of a chunk whose `length` data bytes
+    /// will follow through `write_data`.
+    pub(crate) fn begin_chunk(
+        &mut self,
+        chunk_type: &ChunkType,
+        length: u32,
+    ) -> Result<(), PngError> {
+        if self.open_chunk.is_some() {
+            return Err(PngError::ChunkAlreadyOpen);
+        }
+        if length > Chunk::MAX_LENGTH {
+            return Err(PngError::ChunkLengthTooLarge(length));
+        }
+        let chunk_type = chunk_type.bytes();
+        self.writer
+            .write_all(&length.to_be_bytes())
+            .and_then(|()| self.writer.write_all(&chunk_type))
+            .map_err(PngError::Write)?;
+        // The CRC covers the chunk type as well as the data.
+        let mut crc = Crc32::new();
+        crc.update(&chunk_type);
+        self.open_chunk = Some(OpenChunk {
+            declared: length,
+            written: 0,
+            crc,
+        });
+        Ok(())
+    }
+
+    /// Writes the next piece of the open chunk's data.
+    pub(crate) fn write_data(&mut self, data: &[u8]) -> Result<(), PngError> {
+        let open_chunk = self.open_chunk.as_mut().ok_or(PngError::NoOpenChunk)?;
+        let written = open_chunk.written + data.len() as u64;
+        if written > u64::from(open_chunk.declared) {
+            return Err(PngError::ChunkDataLength {
+                declared: open_chunk.declared,
+                written,
+            });
+        }
+        self.writer.write_all(data).map_err(PngError::Write)?;
+        open_chunk.crc.update(data);
+        open_chunk.written = written;
+        Ok(())
+    }
+
+    /// Writes the CRC of the open chunk, after checking that
+    /// all of its declared data has been written.
+    pub(crate) fn finish_chunk(&mut self) -> Result<(), PngError> {
+        let open_chunk = self.open_chunk.take().ok_or(PngError::NoOpenChunk)?;
+        if open_chunk.written != u64::from(open_chunk.declared) {
+            return Err(PngError::ChunkDataLength {
+                declared: open_chunk.declared,
+                written: open_chunk.written,
+            });
+        }
+        self.writer
+            .write_all(&open_chunk.crc.finalize().to_be_bytes())
+            .map_err(PngError::Write)
+    }
+
+    /// Flushes the writer and returns it.
+    ///
+    /// Fails if a chunk was begun but never finished.
+    pub(crate) fn finish(mut self) -> Result<W, PngError> {
+        if self.open_chunk.is_some() {
+            return Err(PngError::ChunkAlreadyOpen);
+        }
+        self.writer.flush().map_err(PngError::Write)?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod png_writer_tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::test_utils::get_testing_chunks;
+
+    #[test]
+    fn test_write_chunks_matches_png_as_bytes() {
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        for chunk in get_testing_chunks() {
+            writer.write_chunk(&chunk).unwrap();
+        }
+        let written = writer.finish().unwrap();
+
+        let mut expected = Png::PNG_FILE_SIGNATURE.to_vec();
+        for chunk in get_testing_chunks() {
+            expected.extend(chunk.as_bytes());
+        }
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_streamed_chunk_matches_whole_chunk() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let data = "My life is like an eternal night...".as_bytes();
+
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        writer.begin_chunk(&chunk_type, data.len() as u32).unwrap();
+        for piece in data.chunks(4) {
+            writer.write_data(piece).unwrap();
+        }
+        writer.finish_chunk().unwrap();
+        let written = writer.finish().unwrap();
+
+        let mut expected = Png::PNG_FILE_SIGNATURE.to_vec();
+        expected.extend(Chunk::new(chunk_type, data.to_vec()).as_bytes());
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_write_data_past_declared_length() {
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        writer
+            .begin_chunk(&ChunkType::from_str("RuSt").unwrap(), 2)
+            .unwrap();
+        let result = writer.write_data(b"abc");
+        assert!(matches!(
+            result,
+            Err(PngError::ChunkDataLength {
+                declared: 2,
+                written: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_finish_chunk_short_data() {
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        writer
+            .begin_chunk(&ChunkType::from_str("RuSt").unwrap(), 2)
+            .unwrap();
+        writer.write_data(b"a").unwrap();
+        assert!(matches!(
+            writer.finish_chunk(),
+            Err(PngError::ChunkDataLength { .. })
+        ));
+    }
+
+    #[test]
+    fn test_misordered_calls() {
+        let mut writer = PngWriter::new(Vec::new()).unwrap();
+        assert!(matches!(
+            writer.write_data(b"a"),
+            Err(PngError::NoOpenChunk)
+        ));
+        assert!(matches!(writer.finish_chunk(), Err(PngError::NoOpenChunk)));
+
+        writer
+            .begin_chunk(&ChunkType::from_str("RuSt").unwrap(), 0)
+            .unwrap();
+        let chunk = get_testing_chunks().remove(0);
+        assert!(matches!(
+            writer.write_chunk(&chunk),
+            Err(PngError::ChunkAlreadyOpen)
+        ));
+        assert!(matches!(writer.finish(), Err(PngError::ChunkAlreadyOpen)));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::{
+    chunk::Chunk,
+    errors::{ChunkContext, PngError},
+    png::Png,
+};
+
+/// What `Png::parse_lenient` does with a chunk that
+/// fails its CRC check or has an invalid chunk type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CorruptChunkPolicy {
+    /// Keep the chunk's raw bytes as a `CorruptChunk`.
+    Keep,
+    /// Drop the chunk, only recording a diagnostic.
+    Skip,
+}
+
+/// A chunk that couldn't be parsed, kept as raw bytes.
+#[derive(Debug)]
+pub(crate) struct CorruptChunk {
+    /// The zero-based index of the chunk among all chunks in the file,
+    /// valid or not.
+    pub(crate) index: usize,
+    /// The byte offset of the chunk's length field in the file.
+    pub(crate) offset: usize,
+    /// The chunk type bytes as found in the file.
+    pub(crate) chunk_type: [u8; 4],
+    /// The chunk data as found in the file.
+    pub(crate) data: Vec<u8>,
+    /// The CRC as found in the file.
+    pub(crate) crc: u32,
+}
+
+impl CorruptChunk {
+    /// Copies the parts of a chunk found while walking the file.
+    fn from_raw(raw_chunk: &RawChunk) -> Self {
+        Self {
+            index: raw_chunk.index,
+            offset: raw_chunk.offset,
+            chunk_type: raw_chunk.chunk_type(),
+            data: raw_chunk.data().to_vec(),
+            crc: raw_chunk.crc(),
+        }
+    }
+}
+
+/// A chunk found by `Png::walk_chunks`, not checked in any way.
+pub(crate) struct RawChunk<'a> {
+    /// The zero-based index of the chunk among all chunks in the file.
+    pub(crate) index: usize,
+    /// The byte offset of the chunk's length field in the file.
+    pub(crate) offset: usize,
+    /// The bytes of the chunk, from its length field up to and including its CRC.
+    pub(crate) bytes: &'a [u8],
+}
+
+impl RawChunk<'_> {
+    /// The length field as found in the file, which may not match the data.
+    pub(crate) fn declared_length(&self) -> u32 {
+        u32::from_be_bytes(
+            self.bytes[..4]
+                .try_into()
+                .expect("chunk has a length field"),
+        )
+    }
+
+    /// The chunk type bytes as found in the file.
+    pub(crate) fn chunk_type(&self) -> [u8; 4] {
+        self.bytes[4..8].try_into().expect("chunk has a type field")
+    }
+
+    /// The data between the chunk type and the CRC.
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.bytes[8..self.bytes.len() - 4]
+    }
+
+    /// The CRC as found in the file.
+    pub(crate) fn crc(&self) -> u32 {
+        let crc = &self.bytes[self.bytes.len() - 4..];
+        u32::from_be_bytes(crc.try_into().expect("chunk has a CRC"))
+    }
+
+    /// Where the chunk is, for diagnostics.
+    pub(crate) fn context(&self) -> ChunkContext {
+        ChunkContext::new(self.index, Some(self.chunk_type()), self.offset)
+    }
+}
+
+/// The result of `Png::walk_chunks`.
+pub(crate) struct ChunkWalk<'a> {
+    /// Every chunk found, in file order.
+    pub(crate) chunks: Vec<RawChunk<'a>>,
+    /// Why the walk stopped before the end of the file, if it did.
+    pub(crate) error: Option<PngError>,
+}
+
+impl ChunkWalk<'_> {
+    /// The offset right after the last chunk found.
+    pub(crate) fn end(&self) -> usize {
+        self.chunks
+            .last()
+            .map_or(8, |raw_chunk| raw_chunk.offset + raw_chunk.bytes.len())
+    }
+}
+
+/// The result of parsing a PNG with `Png::parse_lenient`.
+pub(crate) struct RecoveredPng {
+    /// The PNG built from every chunk that parsed cleanly.
+    pub(crate) png: Png,
+    /// The chunks that didn't, if they were kept.
+    pub(crate) corrupt_chunks: Vec<CorruptChunk>,
+    /// One error for every problem found, in file order.
+    pub(crate) diagnostics: Vec<PngError>,
+}
+
+impl Png {
+    /// Walks the chunks of `value` in file order, after checking the signature.
+    ///
+    /// `chunk_at(value, offset)` returns the bytes of the chunk at `offset`,
+    /// and so decides where the next chunk starts. Nothing else is checked.
+    /// The walk ends at the end of `value`, or at the first chunk `chunk_at`
+    /// fails on, since there is then no way to find the next one.
+    pub(crate) fn walk_chunks<'a>(
+        value: &'a [u8],
+        chunk_at: impl Fn(&'a [u8], usize) -> Result<&'a [u8], PngError>,
+    ) -> Result<ChunkWalk<'a>, PngError> {
+        Self::signature_of(value)?;
+
+        let mut chunks = Vec::new();
+        let mut offset = 8;
+        while offset < value.len() {
+            match chunk_at(value, offset) {
+                Ok(bytes) => {
+                    chunks.push(RawChunk {
+                        index: chunks.len(),
+                        offset,
+                        bytes,
+                    });
+                    offset += bytes.len();
+                }
+                Err(error) => {
+                    let chunk_type = Self::bytes_at(value, offset + 4).ok();
+                    let context = ChunkContext::new(chunks.len(), chunk_type, offset);
+                    return Ok(ChunkWalk {
+                        chunks,
+                        error: Some(error.in_chunk(context)),
+                    });
+                }
+            }
+        }
+        Ok(ChunkWalk {
+            chunks,
+            error: None,
+        })
+    }
+
+    /// Parses a PNG from a slice of bytes, carrying on past damaged chunks.
+    ///
+    /// A chunk with a wrong CRC or an invalid chunk type is kept or skipped
+    /// according to `policy`, and a diagnostic is recorded for it.
+    /// If a length field is unreadable or runs past the end of the file,
+    /// there is no way to find the next chunk, so parsing stops there with
+    /// a diagnostic and the chunks read so far.
+    /// Only an invalid signature is a hard error.
+    pub(crate) fn parse_lenient(
+        value: &[u8],
+        policy: CorruptChunkPolicy,
+    ) -> Result<RecoveredPng, PngError> {
+        let walk = Self::walk_chunks(value, Self::raw_chunk_at)?;
+
+        let mut chunks = Vec::new();
+        let mut corrupt_chunks = Vec::new();
+        let mut diagnostics = Vec::new();
+        for raw_chunk in &walk.chunks {
+            match Chunk::try_from(raw_chunk.bytes) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(error) => {
+                    diagnostics.push(error.in_chunk(raw_chunk.context()));
+                    if policy == CorruptChunkPolicy::Keep {
+                        corrupt_chunks.push(CorruptChunk::from_raw(raw_chunk));
+                    }
+                }
+            }
+        }
+        diagnostics.extend(walk.error);
+
+        Ok(RecoveredPng {
+            png: Self::from_chunks(chunks),
+            corrupt_chunks,
+            diagnostics,
+        })
+    }
+}
+
+#[cfg(test)]
+mod recovery_tests {
+    use super::*;
+    use crate::test_utils::{get_testing_chunks, get_testing_png_bytes};
+
+    /// The offset of the second testing chunk, right after the first.
+    fn second_chunk() -> usize {
+        8 + get_testing_chunks()[0].as_bytes().len()
+    }
+
+    #[test]
+    fn test_lenient_parse_of_valid_png() {
+        let bytes = get_testing_png_bytes();
+        let recovered = Png::parse_lenient(&bytes, CorruptChunkPolicy::Keep).unwrap();
+        assert_eq!(recovered.png.chunks().len(), 3);
+        assert!(recovered.corrupt_chunks.is_empty());
+        assert!(recovered.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_lenient_parse_keeps_chunk_with_bad_crc() {
+        let mut bytes = get_testing_png_bytes();
+        // Flip the last byte of the second chunk's CRC.
+        let second_chunk_end = second_chunk() + get_testing_chunks()[1].as_bytes().len();
+        bytes[second_chunk_end - 1] ^= 0xFF;
+
+        let recovered = Png::parse_lenient(&bytes, CorruptChunkPolicy::Keep).unwrap();
+        assert_eq!(recovered.png.chunks().len(), 2);
+        assert_eq!(recovered.diagnostics.len(), 1);
+        assert!(matches!(
+            recovered.diagnostics[0].root(),
+            PngError::InvalidCrc(_)
+        ));
+
+        let corrupt = &recovered.corrupt_chunks[0];
+        assert_eq!(corrupt.index, 1);
+        assert_eq!(corrupt.offset, second_chunk());
+        assert_eq!(&corrupt.chunk_type, b"TeAr");
+        assert_eq!(corrupt.data, b"Yes I'm crying");
+    }
+
+    #[test]
+    fn test_lenient_parse_skips_chunk_with_invalid_type() {
+        let mut bytes = get_testing_png_bytes();
+        bytes[second_chunk() + 4] = b'$';
+
+        let recovered = Png::parse_lenient(&bytes, CorruptChunkPolicy::Skip).unwrap();
+        assert!(recovered.png.search_chunk("RaGe").is_some());
+        assert!(recovered.corrupt_chunks.is_empty());
+        assert!(matches!(
+            recovered.diagnostics[0].root(),
+            PngError::InvalidByte
+        ));
+    }
+
+    #[test]
+    fn test_lenient_parse_stops_at_truncation() {
+        let bytes = get_testing_png_bytes();
+        let truncated = &bytes[..bytes.len() - 2];
+
+        let recovered = Png::parse_lenient(truncated, CorruptChunkPolicy::Keep).unwrap();
+        assert_eq!(recovered.png.chunks().len(), 2);
+        assert!(matches!(
+            recovered.diagnostics[0].root(),
+            PngError::ChunkLengthExceedsFile { .. }
+        ));
+    }
+
+    #[test]
+    fn test_lenient_parse_rejects_bad_signature() {
+        let mut bytes = get_testing_png_bytes();
+        bytes[0] = 0;
+        let recovered = Png::parse_lenient(&bytes, CorruptChunkPolicy::Keep);
+        assert!(matches!(recovered, Err(PngError::InvalidPngSignature)));
+    }
+}
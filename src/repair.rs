@@ -0,0 +1,420 @@
+use std::fmt::Display;
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    crc32::calculate_crc,
+    errors::{ChunkContext, PngError},
+    png::Png,
+    recovery::RawChunk,
+};
+
+/// How far a length field may be off for `Png::repair` to still
+/// find the real end of the chunk.
+const MAX_LENGTH_DRIFT: u32 = 16;
+
+/// A change `Png::repair` made to a file.
+#[derive(Debug)]
+pub(crate) enum Repair {
+    /// The stored CRC didn't match the chunk and was recomputed.
+    RecomputedCrc {
+        context: ChunkContext,
+        old: u32,
+        new: u32,
+    },
+    /// The length field didn't match where the next chunk starts.
+    FixedLength {
+        context: ChunkContext,
+        old: u32,
+        new: u32,
+    },
+    /// The chunk type wasn't valid, but the stored CRC matched
+    /// this chunk type with one byte changed.
+    RecoveredChunkType {
+        context: ChunkContext,
+        new: ChunkType,
+    },
+    /// The chunk type wasn't valid and couldn't be recovered from
+    /// the CRC, so the chunk was dropped.
+    DroppedChunk { context: ChunkContext },
+    /// An intact chunk came after `IEND`, so it was moved before it.
+    MovedBeforeIend { context: ChunkContext },
+    /// Bytes after `IEND`, or a truncated chunk at the end of the file.
+    DroppedTrailingBytes { offset: usize, count: usize },
+    /// The file didn't end with an `IEND` chunk, so one was added.
+    AddedIend,
+}
+
+impl Repair {
+    /// Everything from `offset` to the end of the file.
+    fn trailing_bytes(value: &[u8], offset: usize) -> Self {
+        Self::DroppedTrailingBytes {
+            offset,
+            count: value.len() - offset,
+        }
+    }
+}
+
+impl Display for Repair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RecomputedCrc { context, old, new } => {
+                write!(f, "{context}: replaced CRC {old:#010x} with {new:#010x}")
+            }
+            Self::FixedLength { context, old, new } => {
+                write!(f, "{context}: changed length from {old} to {new}")
+            }
+            Self::RecoveredChunkType { context, new } => {
+                write!(f, "{context}: recovered chunk type {new} from the CRC")
+            }
+            Self::DroppedChunk { context } => write!(
+                f,
+                "{context}: dropped chunk with an invalid chunk type the CRC doesn't recover"
+            ),
+            Self::MovedBeforeIend { context } => write!(f, "{context}: moved before IEND"),
+            Self::DroppedTrailingBytes { offset, count } => {
+                write!(f, "dropped {count} trailing bytes at offset {offset:#X}")
+            }
+            Self::AddedIend => write!(f, "added missing IEND chunk"),
+        }
+    }
+}
+
+/// The result of `Png::repair`.
+pub(crate) struct RepairedPng {
+    pub(crate) png: Png,
+    /// Every change made, in file order.
+    pub(crate) repairs: Vec<Repair>,
+}
+
+impl Png {
+    /// Rebuilds a damaged PNG from a slice of bytes.
+    ///
+    /// The file is walked like in `Png::parse_lenient`, but instead of
+    /// giving up on a damaged chunk this:
+    /// - recomputes CRCs that don't match the chunk,
+    /// - fixes length fields that are off by up to 16 bytes, by looking for
+    ///   the length at which the CRC matches or the next chunk type is valid,
+    /// - recovers a chunk type with one damaged byte from the stored CRC,
+    ///   and drops the chunk if that fails,
+    /// - moves intact chunks found after `IEND` before it, and drops
+    ///   everything from the first bytes there that aren't an intact chunk,
+    ///   as well as any truncated chunk at the end,
+    /// - adds `IEND` if it's missing.
+    ///
+    /// Only an invalid signature is a hard error.
+    pub(crate) fn repair(value: &[u8]) -> Result<RepairedPng, PngError> {
+        let walk = Self::walk_chunks(value, Self::resynced_chunk_at)?;
+
+        let mut chunks: Vec<Chunk> = Vec::new();
+        let mut after_iend = Vec::new();
+        let mut repairs = Vec::new();
+        let mut end = walk.end();
+        for raw_chunk in &walk.chunks {
+            if chunks
+                .last()
+                .is_some_and(|chunk| chunk.chunk_type().bytes() == *b"IEND")
+            {
+                // Files written by older versions of `encode` keep their
+                // message here, so only what isn't a chunk is junk.
+                match Self::intact_chunk(raw_chunk) {
+                    Some(chunk) if chunk.chunk_type().bytes() != *b"IEND" => {
+                        repairs.push(Repair::MovedBeforeIend {
+                            context: raw_chunk.context(),
+                        });
+                        after_iend.push(chunk);
+                        continue;
+                    }
+                    _ => {
+                        end = raw_chunk.offset;
+                        break;
+                    }
+                }
+            }
+            let chunk_type = match ChunkType::try_from(raw_chunk.chunk_type()) {
+                Ok(chunk_type) => chunk_type,
+                Err(_) => match Self::recover_chunk_type(raw_chunk) {
+                    Some(chunk_type) => {
+                        repairs.push(Repair::RecoveredChunkType {
+                            context: raw_chunk.context(),
+                            new: chunk_type.clone(),
+                        });
+                        chunk_type
+                    }
+                    None => {
+                        repairs.push(Repair::DroppedChunk {
+                            context: raw_chunk.context(),
+                        });
+                        continue;
+                    }
+                },
+            };
+
+            let data = raw_chunk.data();
+            let declared = raw_chunk.declared_length();
+            if data.len() != declared as usize {
+                repairs.push(Repair::FixedLength {
+                    context: raw_chunk.context(),
+                    old: declared,
+                    new: data.len() as u32,
+                });
+            }
+            let chunk = Chunk::new(chunk_type, data.to_vec());
+            if chunk.crc() != raw_chunk.crc() {
+                repairs.push(Repair::RecomputedCrc {
+                    context: raw_chunk.context(),
+                    old: raw_chunk.crc(),
+                    new: chunk.crc(),
+                });
+            }
+            chunks.push(chunk);
+        }
+        if end < value.len() {
+            repairs.push(Repair::trailing_bytes(value, end));
+        }
+        if !after_iend.is_empty() {
+            let iend = chunks.len() - 1;
+            chunks.splice(iend..iend, after_iend);
+        }
+
+        let ends_with_iend = chunks
+            .last()
+            .is_some_and(|chunk| chunk.chunk_type().bytes() == *b"IEND");
+        if !ends_with_iend {
+            let iend = ChunkType::try_from(*b"IEND").expect("IEND is a valid chunk type");
+            chunks.push(Chunk::new(iend, Vec::new()));
+            repairs.push(Repair::AddedIend);
+        }
+
+        Ok(RepairedPng {
+            png: Self::from_chunks(chunks),
+            repairs,
+        })
+    }
+
+    /// The chunk, if its chunk type is valid and its length
+    /// and CRC match its data.
+    fn intact_chunk(raw_chunk: &RawChunk) -> Option<Chunk> {
+        let chunk_type = ChunkType::try_from(raw_chunk.chunk_type()).ok()?;
+        let chunk = Chunk::new(chunk_type, raw_chunk.data().to_vec());
+        (chunk.length() == raw_chunk.declared_length() && chunk.crc() == raw_chunk.crc())
+            .then_some(chunk)
+    }
+
+    /// Finds the valid chunk type the stored CRC was computed over,
+    /// assuming only one byte of the chunk type is damaged.
+    fn recover_chunk_type(raw_chunk: &RawChunk) -> Option<ChunkType> {
+        let damaged = raw_chunk.chunk_type();
+        (0..4)
+            .flat_map(|position| {
+                (b'A'..=b'Z').chain(b'a'..=b'z').map(move |letter| {
+                    let mut bytes = damaged;
+                    bytes[position] = letter;
+                    bytes
+                })
+            })
+            .filter_map(|bytes| ChunkType::try_from(bytes).ok())
+            .find(|chunk_type| calculate_crc(chunk_type, raw_chunk.data()) == raw_chunk.crc())
+    }
+
+    /// Finds the bytes of the chunk at `starting_cursor` like `raw_chunk_at`,
+    /// but with the length `find_chunk_length` works out.
+    fn resynced_chunk_at(value: &[u8], starting_cursor: usize) -> Result<&[u8], PngError> {
+        let header: [u8; 8] = Self::bytes_at(value, starting_cursor)?;
+        let declared = u32::from_be_bytes(header[..4].try_into().expect("4 bytes"));
+        let type_bytes: [u8; 4] = header[4..].try_into().expect("4 bytes");
+        // Without a trustworthy chunk type there's no telling whether
+        // the length is right either, so the chunk is taken as declared.
+        // If no length fits in the file, neither does the declared one,
+        // and `raw_chunk_at` reports why.
+        let length = ChunkType::try_from(type_bytes).ok().and_then(|chunk_type| {
+            Self::find_chunk_length(value, starting_cursor, &chunk_type, declared)
+        });
+        match length {
+            Some(length) => {
+                let end = starting_cursor + Self::MIN_CHUNK_LENGTH + length as usize;
+                Ok(&value[starting_cursor..end])
+            }
+            None => Self::raw_chunk_at(value, starting_cursor),
+        }
+    }
+
+    /// Works out the real data length of the chunk at `starting_cursor`.
+    ///
+    /// The declared length wins if the chunk's CRC matches or the next chunk
+    /// starts right after it. Otherwise lengths up to `MAX_LENGTH_DRIFT`
+    /// away are tried, first looking for a matching CRC, which is very
+    /// unlikely by chance, then for a valid chunk type right after.
+    /// Returns `None` if no length fits in the file.
+    fn find_chunk_length(
+        value: &[u8],
+        starting_cursor: usize,
+        chunk_type: &ChunkType,
+        declared: u32,
+    ) -> Option<u32> {
+        let fits = |length: u32| {
+            length <= Chunk::MAX_LENGTH
+                && starting_cursor + Self::MIN_CHUNK_LENGTH + length as usize <= value.len()
+        };
+        let crc_matches = |length: u32| {
+            let data_start = starting_cursor + 8;
+            let data_end = data_start + length as usize;
+            Self::bytes_at::<4>(value, data_end).is_ok_and(|crc| {
+                calculate_crc(chunk_type, &value[data_start..data_end]) == u32::from_be_bytes(crc)
+            })
+        };
+        let next_chunk_starts = |length: u32| {
+            let end = starting_cursor + Self::MIN_CHUNK_LENGTH + length as usize;
+            end == value.len()
+                || Self::bytes_at::<4>(value, end + 4)
+                    .is_ok_and(|bytes| ChunkType::try_from(bytes).is_ok())
+        };
+
+        if fits(declared) && (crc_matches(declared) || next_chunk_starts(declared)) {
+            return Some(declared);
+        }
+        let candidates = || {
+            (1..=MAX_LENGTH_DRIFT)
+                .flat_map(|drift| [declared.checked_add(drift), declared.checked_sub(drift)])
+                .flatten()
+                .filter(|&length| fits(length))
+        };
+        candidates()
+            .find(|&length| crc_matches(length))
+            .or_else(|| candidates().find(|&length| next_chunk_starts(length)))
+            .or_else(|| fits(declared).then_some(declared))
+    }
+}
+
+#[cfg(test)]
+mod repair_tests {
+    use super::*;
+    use crate::test_utils::get_testing_png;
+
+    // IHDR is 12 + 6 bytes long, so the message chunk starts at 8 + 18.
+    const MESSAGE_CHUNK: usize = 26;
+
+    #[test]
+    fn test_repair_valid_png_changes_nothing() {
+        let bytes = get_testing_png().as_bytes();
+        let repaired = Png::repair(&bytes).unwrap();
+        assert!(repaired.repairs.is_empty());
+        assert_eq!(repaired.png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_repair_recomputes_crc() {
+        let expected = get_testing_png().as_bytes();
+        let mut bytes = expected.clone();
+        // The last byte of the message chunk's CRC.
+        bytes[MESSAGE_CHUNK + 12 + 14 - 1] ^= 0xFF;
+
+        let repaired = Png::repair(&bytes).unwrap();
+        assert_eq!(repaired.repairs.len(), 1);
+        assert!(matches!(repaired.repairs[0], Repair::RecomputedCrc { .. }));
+        assert_eq!(repaired.png.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_repair_fixes_length() {
+        let expected = get_testing_png().as_bytes();
+        for wrong_length in [10u32, 17] {
+            let mut bytes = expected.clone();
+            bytes[MESSAGE_CHUNK..MESSAGE_CHUNK + 4].copy_from_slice(&wrong_length.to_be_bytes());
+
+            let repaired = Png::repair(&bytes).unwrap();
+            assert_eq!(repaired.repairs.len(), 1);
+            assert!(matches!(
+                repaired.repairs[0],
+                Repair::FixedLength { old, new: 14, .. } if old == wrong_length
+            ));
+            assert_eq!(repaired.png.as_bytes(), expected);
+        }
+    }
+
+    #[test]
+    fn test_repair_adds_missing_iend() {
+        let expected = get_testing_png().as_bytes();
+        let without_iend = &expected[..expected.len() - 12];
+
+        let repaired = Png::repair(without_iend).unwrap();
+        assert!(matches!(repaired.repairs[..], [Repair::AddedIend]));
+        assert_eq!(repaired.png.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_repair_drops_trailing_junk() {
+        let expected = get_testing_png().as_bytes();
+        let mut bytes = expected.clone();
+        bytes.extend_from_slice(b"junk after the end");
+
+        let repaired = Png::repair(&bytes).unwrap();
+        assert!(matches!(
+            repaired.repairs[..],
+            [Repair::DroppedTrailingBytes { count: 18, .. }]
+        ));
+        assert_eq!(repaired.png.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_repair_moves_chunks_after_iend() {
+        let expected = get_testing_png().as_bytes();
+        let mut png = get_testing_png();
+        let message = png.remove_chunk("ruSt").unwrap();
+        png.append_chunk(message);
+        let mut bytes = png.as_bytes();
+        bytes.extend_from_slice(b"junk");
+
+        let repaired = Png::repair(&bytes).unwrap();
+        assert!(matches!(
+            repaired.repairs[..],
+            [
+                Repair::MovedBeforeIend { .. },
+                Repair::DroppedTrailingBytes { count: 4, .. }
+            ]
+        ));
+        assert_eq!(repaired.png.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_repair_recovers_chunk_type_from_crc() {
+        let expected = get_testing_png().as_bytes();
+        let mut bytes = expected.clone();
+        bytes[MESSAGE_CHUNK + 4] = b'$';
+
+        let repaired = Png::repair(&bytes).unwrap();
+        assert!(matches!(
+            &repaired.repairs[..],
+            [Repair::RecoveredChunkType { new, .. }] if new.to_string() == "ruSt"
+        ));
+        assert_eq!(repaired.png.as_bytes(), expected);
+    }
+
+    #[test]
+    fn test_repair_drops_chunk_with_unrecoverable_type() {
+        let mut bytes = get_testing_png().as_bytes();
+        bytes[MESSAGE_CHUNK + 4] = b'$';
+        bytes[MESSAGE_CHUNK + 5] = b'$';
+
+        let repaired = Png::repair(&bytes).unwrap();
+        assert!(matches!(
+            repaired.repairs[..],
+            [Repair::DroppedChunk { .. }]
+        ));
+        let bytes = repaired.png.as_bytes();
+        assert_eq!(Png::try_from(bytes.as_ref()).unwrap().chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_repair_display() {
+        let repair = Repair::FixedLength {
+            context: ChunkContext::new(1, Some(*b"ruSt"), 0x1A),
+            old: 10,
+            new: 14,
+        };
+        assert_eq!(
+            repair.to_string(),
+            "chunk #1 'ruSt' at offset 0x1A: changed length from 10 to 14"
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::PngError,
+    ihdr::{ColorType, Ihdr},
+    palette::Palette,
+    typed_chunk::{be_u16, check_samples, DependentChunk},
+};
+
+/// The default background color to show the image against (`bKGD`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Background {
+    /// A gray sample, for grayscale images with or without alpha.
+    Gray(u16),
+    /// RGB samples, for RGB images with or without alpha.
+    Rgb { red: u16, green: u16, blue: u16 },
+    /// A palette index, for indexed images.
+    Indexed(u8),
+}
+
+impl Background {
+    /// Checks that an indexed background points into the palette.
+    pub(crate) fn validate_palette(&self, palette: &Palette) -> Result<(), PngError> {
+        match *self {
+            Self::Indexed(index) if usize::from(index) >= palette.entries().len() => {
+                Err(Self::invalid(format_args!(
+                    "palette index {index} is out of range for {} entries",
+                    palette.entries().len()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DependentChunk for Background {
+    const CHUNK_TYPE: [u8; 4] = *b"bKGD";
+
+    fn from_data(data: &[u8], ihdr: &Ihdr) -> Result<Self, PngError> {
+        let background = match ihdr.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => {
+                Self::check_length(data, 2)?;
+                Self::Gray(be_u16(data, 0))
+            }
+            ColorType::Rgb | ColorType::Rgba => {
+                Self::check_length(data, 6)?;
+                Self::Rgb {
+                    red: be_u16(data, 0),
+                    green: be_u16(data, 2),
+                    blue: be_u16(data, 4),
+                }
+            }
+            ColorType::Indexed => {
+                Self::check_length(data, 1)?;
+                return Ok(Self::Indexed(data[0]));
+            }
+        };
+        check_samples::<Self>(data, ihdr.bit_depth)?;
+        Ok(background)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        match *self {
+            Self::Gray(gray) => gray.to_be_bytes().to_vec(),
+            Self::Rgb { red, green, blue } => [red, green, blue]
+                .into_iter()
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+            Self::Indexed(index) => vec![index],
+        }
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Gray(gray) => write!(f, "background gray {gray}"),
+            Self::Rgb { red, green, blue } => {
+                write!(f, "background color ({red}, {green}, {blue})")
+            }
+            Self::Indexed(index) => write!(f, "background palette entry {index}"),
+        }
+    }
+}
+
+/// The number of bits that were significant in each channel of
+/// the original image, before it was scaled to the PNG bit depth (`sBIT`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SignificantBits {
+    color_type: ColorType,
+    bits: Vec<u8>,
+}
+
+impl SignificantBits {
+    /// The channels sBIT has a value for, in order. Indexed images
+    /// have one for each channel of their palette.
+    fn channel_names(color_type: ColorType) -> &'static [&'static str] {
+        match color_type {
+            ColorType::Grayscale => &["gray"],
+            ColorType::Rgb | ColorType::Indexed => &["red", "green", "blue"],
+            ColorType::GrayscaleAlpha => &["gray", "alpha"],
+            ColorType::Rgba => &["red", "green", "blue", "alpha"],
+        }
+    }
+
+    /// Returns the significant bits of each channel, in the order
+    /// of `channel_names`.
+    #[allow(
+        dead_code,
+        reason = "kept for pixel decoding, print only needs Display so far"
+    )]
+    pub(crate) fn bits(&self) -> &[u8] {
+        &self.bits
+    }
+}
+
+impl DependentChunk for SignificantBits {
+    const CHUNK_TYPE: [u8; 4] = *b"sBIT";
+
+    fn from_data(data: &[u8], ihdr: &Ihdr) -> Result<Self, PngError> {
+        Self::check_length(data, Self::channel_names(ihdr.color_type).len())?;
+        // Palette entries always have 8-bit channels.
+        let sample_depth = match ihdr.color_type {
+            ColorType::Indexed => 8,
+            _ => ihdr.bit_depth,
+        };
+        if let Some(&bits) = data
+            .iter()
+            .find(|&&bits| !(1..=sample_depth).contains(&bits))
+        {
+            return Err(Self::invalid(format_args!(
+                "significant bits must be between 1 and {sample_depth}, got {bits}"
+            )));
+        }
+        Ok(Self {
+            color_type: ihdr.color_type,
+            bits: data.to_vec(),
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        self.bits.clone()
+    }
+}
+
+impl Display for SignificantBits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "significant bits:")?;
+        let names = Self::channel_names(self.color_type);
+        for (index, (name, bits)) in names.iter().zip(&self.bits).enumerate() {
+            let separator = if index == 0 { "" } else { "," };
+            write!(f, "{separator} {name} {bits}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod samples_tests {
+    use super::*;
+    use crate::{test_utils::ihdr, typed_chunk::TypedChunk};
+
+    #[test]
+    fn test_background_layouts() {
+        let gray = Background::from_data(&[0, 200], &ihdr(ColorType::GrayscaleAlpha, 8)).unwrap();
+        assert_eq!(gray, Background::Gray(200));
+        assert_eq!(gray.to_string(), "background gray 200");
+
+        let data = [0, 1, 0, 2, 0, 3];
+        let rgb = Background::from_data(&data, &ihdr(ColorType::Rgba, 8)).unwrap();
+        assert_eq!(rgb.to_string(), "background color (1, 2, 3)");
+        assert_eq!(rgb.to_data(), data);
+
+        let indexed = Background::from_data(&[4], &ihdr(ColorType::Indexed, 4)).unwrap();
+        assert_eq!(indexed, Background::Indexed(4));
+        assert_eq!(indexed.to_string(), "background palette entry 4");
+    }
+
+    #[test]
+    fn test_background_checks() {
+        assert!(Background::from_data(&[0, 2], &ihdr(ColorType::Grayscale, 1)).is_err());
+        assert!(Background::from_data(&[0, 1], &ihdr(ColorType::Rgb, 8)).is_err());
+        assert!(Background::from_data(&[0, 0], &ihdr(ColorType::Indexed, 8)).is_err());
+
+        let palette = Palette::from_data(&[0; 6]).unwrap();
+        assert!(Background::Indexed(1).validate_palette(&palette).is_ok());
+        assert!(Background::Indexed(2).validate_palette(&palette).is_err());
+    }
+
+    #[test]
+    fn test_significant_bits() {
+        let bits = SignificantBits::from_data(&[5, 6, 5], &ihdr(ColorType::Rgb, 8)).unwrap();
+        assert_eq!(bits.bits(), [5, 6, 5]);
+        assert_eq!(bits.to_data(), [5, 6, 5]);
+        assert_eq!(bits.to_string(), "significant bits: red 5, green 6, blue 5");
+
+        let gray = SignificantBits::from_data(&[3, 8], &ihdr(ColorType::GrayscaleAlpha, 8));
+        assert_eq!(
+            gray.unwrap().to_string(),
+            "significant bits: gray 3, alpha 8"
+        );
+    }
+
+    #[test]
+    fn test_significant_bits_checks() {
+        assert!(SignificantBits::from_data(&[5, 6], &ihdr(ColorType::Rgb, 8)).is_err());
+        assert!(SignificantBits::from_data(&[0], &ihdr(ColorType::Grayscale, 8)).is_err());
+        assert!(SignificantBits::from_data(&[3], &ihdr(ColorType::Grayscale, 2)).is_err());
+        // Palette entries have 8-bit channels, whatever the bit depth.
+        assert!(SignificantBits::from_data(&[8, 8, 8], &ihdr(ColorType::Indexed, 1)).is_ok());
+    }
+}
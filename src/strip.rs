@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, errors::PngError, png::Png};
+
+/// Ancillary chunks that change how the image looks, kept by default.
+const RENDERING_CHUNKS: [[u8; 4]; 8] = [
+    *b"gAMA", *b"cHRM", *b"sRGB", *b"iCCP", *b"sBIT", *b"tRNS", *b"bKGD", *b"pHYs",
+];
+
+/// Ancillary chunks that can identify the author, camera or place,
+/// which are the point of stripping.
+const METADATA_CHUNKS: [[u8; 4]; 5] = [*b"tEXt", *b"zTXt", *b"iTXt", *b"eXIf", *b"tIME"];
+
+/// Why `StripPolicy` keeps or drops a chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StripReason {
+    /// Critical chunks are needed to show the image at all.
+    Critical,
+    /// On the default allowlist of chunks that affect rendering.
+    Rendering,
+    /// Asked for with `--keep`.
+    Kept,
+    /// Asked for with `--drop`.
+    Dropped,
+    /// Text, EXIF or time metadata.
+    Metadata,
+    /// Anything else. The chunk's property bits are listed so unknown
+    /// chunks can be judged before deciding to `--keep` them.
+    NotAllowed { public: bool, safe_to_copy: bool },
+}
+
+impl StripReason {
+    /// Whether the chunk stays in the image.
+    pub(crate) fn keeps(self) -> bool {
+        matches!(self, Self::Critical | Self::Rendering | Self::Kept)
+    }
+}
+
+impl Display for StripReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Critical => write!(f, "critical"),
+            Self::Rendering => write!(f, "affects rendering"),
+            Self::Kept => write!(f, "kept with --keep"),
+            Self::Dropped => write!(f, "dropped with --drop"),
+            Self::Metadata => write!(f, "metadata"),
+            Self::NotAllowed {
+                public,
+                safe_to_copy,
+            } => write!(
+                f,
+                "{} chunk not on the allowlist, {}",
+                if *public { "public" } else { "private" },
+                if *safe_to_copy {
+                    "safe to copy"
+                } else {
+                    "unsafe to copy"
+                }
+            ),
+        }
+    }
+}
+
+/// Which ancillary chunks `strip` removes: everything but the rendering
+/// chunks, adjusted by the chunk types to keep or drop.
+#[derive(Debug, Default)]
+pub(crate) struct StripPolicy {
+    keep: Vec<ChunkType>,
+    drop: Vec<ChunkType>,
+}
+
+impl StripPolicy {
+    /// Parses the chunk types to keep or drop on top of the defaults.
+    /// Critical chunks can't be dropped, and no type can be in both lists.
+    pub(crate) fn new(keep: &[String], drop: &[String]) -> Result<Self, PngError> {
+        let parse = |types: &[String]| -> Result<Vec<ChunkType>, PngError> {
+            types.iter().map(|t| ChunkType::from_str(t)).collect()
+        };
+        let (keep, drop) = (parse(keep)?, parse(drop)?);
+        for chunk_type in &drop {
+            if chunk_type.is_critical() {
+                return Err(PngError::InvalidStripPolicy(format!(
+                    "{chunk_type} is critical and can't be dropped"
+                )));
+            }
+            if keep.contains(chunk_type) {
+                return Err(PngError::InvalidStripPolicy(format!(
+                    "{chunk_type} can't be both kept and dropped"
+                )));
+            }
+        }
+        Ok(Self { keep, drop })
+    }
+
+    /// Decides whether a chunk of the given type is kept, and why.
+    pub(crate) fn reason(&self, chunk_type: &ChunkType) -> StripReason {
+        let bytes = chunk_type.bytes();
+        if chunk_type.is_critical() {
+            StripReason::Critical
+        } else if self.drop.contains(chunk_type) {
+            StripReason::Dropped
+        } else if self.keep.contains(chunk_type) {
+            StripReason::Kept
+        } else if RENDERING_CHUNKS.contains(&bytes) {
+            StripReason::Rendering
+        } else if METADATA_CHUNKS.contains(&bytes) {
+            StripReason::Metadata
+        } else {
+            StripReason::NotAllowed {
+                public: chunk_type.is_public(),
+                safe_to_copy: chunk_type.is_safe_to_copy(),
+            }
+        }
+    }
+}
+
+impl Png {
+    /// Removes every chunk `policy` drops, returning them in file order.
+    pub(crate) fn strip(&mut self, policy: &StripPolicy) -> Vec<Chunk> {
+        self.remove_chunks_where(|chunk| !policy.reason(chunk.chunk_type()).keeps())
+    }
+}
+
+#[cfg(test)]
+mod strip_tests {
+    use super::*;
+    use crate::test_utils::chunk;
+
+    fn chunk_type(chunk_type: &str) -> ChunkType {
+        ChunkType::from_str(chunk_type).unwrap()
+    }
+
+    fn types(chunks: &[Chunk]) -> Vec<String> {
+        chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = StripPolicy::default();
+        assert_eq!(policy.reason(&chunk_type("IHDR")), StripReason::Critical);
+        assert_eq!(policy.reason(&chunk_type("iCCP")), StripReason::Rendering);
+        assert_eq!(policy.reason(&chunk_type("eXIf")), StripReason::Metadata);
+        let private = policy.reason(&chunk_type("ruSt"));
+        assert!(!private.keeps());
+        assert_eq!(
+            private.to_string(),
+            "private chunk not on the allowlist, safe to copy"
+        );
+        assert!(policy.reason(&chunk_type("bKGD")).keeps());
+        assert!(!policy.reason(&chunk_type("hIST")).keeps());
+    }
+
+    #[test]
+    fn test_keep_and_drop() {
+        let policy = StripPolicy::new(&["tEXt".to_string()], &["pHYs".to_string()]).unwrap();
+        assert_eq!(policy.reason(&chunk_type("tEXt")), StripReason::Kept);
+        assert_eq!(policy.reason(&chunk_type("pHYs")), StripReason::Dropped);
+        assert!(policy.reason(&chunk_type("tEXt")).keeps());
+        assert!(!policy.reason(&chunk_type("pHYs")).keeps());
+    }
+
+    #[test]
+    fn test_invalid_policies() {
+        assert!(matches!(
+            StripPolicy::new(&[], &["IDAT".to_string()]),
+            Err(PngError::InvalidStripPolicy(_))
+        ));
+        assert!(matches!(
+            StripPolicy::new(&["tIME".to_string()], &["tIME".to_string()]),
+            Err(PngError::InvalidStripPolicy(_))
+        ));
+        assert!(StripPolicy::new(&["tIM".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_png_strip() {
+        let mut png = Png::from_chunks(
+            [
+                "IHDR", "gAMA", "tEXt", "ruSt", "IDAT", "tIME", "tEXt", "IEND",
+            ]
+            .into_iter()
+            .map(chunk)
+            .collect(),
+        );
+        let removed = png.strip(&StripPolicy::default());
+        assert_eq!(types(&removed), ["tEXt", "ruSt", "tIME", "tEXt"]);
+        assert_eq!(types(png.chunks()), ["IHDR", "gAMA", "IDAT", "IEND"]);
+        assert!(png.strip(&StripPolicy::default()).is_empty());
+    }
+}
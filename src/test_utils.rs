@@ -0,0 +1,57 @@
+//! Fixtures shared by the unit tests of every module.
+
+use std::str::FromStr;
+
+use crate::{
+    chunk::Chunk,
+    chunk_type::ChunkType,
+    ihdr::{ColorType, Ihdr, InterlaceMethod},
+    png::Png,
+};
+
+/// A chunk of the given type holding `data`.
+pub(crate) fn chunk_with_data(chunk_type: &str, data: &[u8]) -> Chunk {
+    Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+}
+
+/// An empty chunk of the given type.
+pub(crate) fn chunk(chunk_type: &str) -> Chunk {
+    chunk_with_data(chunk_type, &[])
+}
+
+/// Three private chunks, each holding a short message.
+pub(crate) fn get_testing_chunks() -> Vec<Chunk> {
+    vec![
+        chunk_with_data("RuSt", b"I don't know what I'm doing"),
+        chunk_with_data("TeAr", b"Yes I'm crying"),
+        chunk_with_data("RaGe", b"Nooooooo"),
+    ]
+}
+
+/// The bytes of a PNG made of `get_testing_chunks`.
+pub(crate) fn get_testing_png_bytes() -> Vec<u8> {
+    Png::from_chunks(get_testing_chunks()).as_bytes()
+}
+
+/// A PNG of a short chunk typed IHDR, a message chunk and IEND,
+/// small enough to count its bytes by hand.
+pub(crate) fn get_testing_png() -> Png {
+    Png::from_chunks(vec![
+        chunk_with_data("IHDR", b"header"),
+        chunk_with_data("ruSt", b"hidden message"),
+        chunk("IEND"),
+    ])
+}
+
+/// The header of a 1x1 image with the given color type and bit depth.
+pub(crate) fn ihdr(color_type: ColorType, bit_depth: u8) -> Ihdr {
+    Ihdr {
+        width: 1,
+        height: 1,
+        bit_depth,
+        color_type,
+        compression_method: 0,
+        filter_method: 0,
+        interlace_method: InterlaceMethod::None,
+    }
+}
@@ -0,0 +1,849 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, errors::PngError, png::Png, typed_chunk::TypedChunk, zlib};
+
+/// The longest keyword the spec allows, in characters.
+pub(crate) const MAX_KEYWORD_LENGTH: usize = 79;
+/// Values longer than this many bytes are compressed by `Png::set_text`.
+const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// Decodes ISO-8859-1 bytes, which map one to one onto the first
+/// 256 Unicode code points.
+pub(crate) fn latin1_to_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| char::from(byte)).collect()
+}
+
+/// Encodes `value` as ISO-8859-1, or returns `None`
+/// if it has characters outside of it.
+pub(crate) fn string_to_latin1(value: &str) -> Option<Vec<u8>> {
+    value.chars().map(|c| u8::try_from(c).ok()).collect()
+}
+
+/// Checks a keyword against the rules shared by every chunk that has one:
+/// 1 to 79 printable Latin-1 characters, without leading, trailing
+/// or consecutive spaces.
+pub(crate) fn check_keyword<T: TypedChunk>(keyword: &str) -> Result<(), PngError> {
+    let length = keyword.chars().count();
+    if !(1..=MAX_KEYWORD_LENGTH).contains(&length) {
+        return Err(T::invalid(format_args!(
+            "keyword must be 1 to {MAX_KEYWORD_LENGTH} characters long, got {length}"
+        )));
+    }
+    if let Some(c) = keyword
+        .chars()
+        .find(|&c| !matches!(u32::from(c), 32..=126 | 161..=255))
+    {
+        return Err(T::invalid(format_args!(
+            "keyword {:?} has the character {:?}, which isn't printable Latin-1",
+            keyword, c
+        )));
+    }
+    if keyword.starts_with(' ') || keyword.ends_with(' ') || keyword.contains("  ") {
+        return Err(T::invalid(format_args!(
+            "keyword {keyword:?} has leading, trailing or consecutive spaces"
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that `text` can be stored as Latin-1 and has no NULs.
+pub(crate) fn check_text<T: TypedChunk>(text: &str) -> Result<(), PngError> {
+    match text.chars().find(|&c| c == '\0' || u32::from(c) > 255) {
+        Some(c) => Err(T::invalid(format_args!(
+            "text has the character {c:?}, which can't be stored as Latin-1"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Splits chunk data at the NUL that ends the keyword, returning
+/// the checked keyword and the bytes after the NUL.
+pub(crate) fn split_keyword<T: TypedChunk>(data: &[u8]) -> Result<(String, &[u8]), PngError> {
+    let separator = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| T::invalid("no NUL separator after the keyword"))?;
+    let keyword = latin1_to_string(&data[..separator]);
+    check_keyword::<T>(&keyword)?;
+    Ok((keyword, &data[separator + 1..]))
+}
+
+/// A keyword and its Latin-1 text, the layout `tEXt` and `zTXt` share.
+/// `COMPRESSED` says which of the two it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Latin1TextChunk<const COMPRESSED: bool> {
+    keyword: String,
+    text: String,
+}
+
+/// An uncompressed Latin-1 text chunk (`tEXt`), such as
+/// `Title`, `Author` or `Comment`.
+pub(crate) type TextChunk = Latin1TextChunk<false>;
+
+/// A zlib-compressed Latin-1 text chunk (`zTXt`),
+/// used for long values like XMP packets.
+pub(crate) type ZTxtChunk = Latin1TextChunk<true>;
+
+impl<const COMPRESSED: bool> Latin1TextChunk<COMPRESSED>
+where
+    Self: TypedChunk,
+{
+    /// Creates a text chunk, checking the keyword and that
+    /// the text can be stored as Latin-1 without NULs.
+    pub(crate) fn new(keyword: &str, text: &str) -> Result<Self, PngError> {
+        check_keyword::<Self>(keyword)?;
+        check_text::<Self>(text)?;
+        Ok(Self {
+            keyword: keyword.to_string(),
+            text: text.to_string(),
+        })
+    }
+
+    pub(crate) fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Builds the chunk from a checked keyword and the text bytes.
+    fn from_latin1(keyword: String, text: &[u8]) -> Result<Self, PngError> {
+        if text.contains(&0) {
+            return Err(Self::invalid("text has a NUL"));
+        }
+        Ok(Self {
+            keyword,
+            text: latin1_to_string(text),
+        })
+    }
+
+    /// Encodes the keyword and its NUL separator.
+    fn keyword_to_latin1(&self) -> Vec<u8> {
+        let mut data = string_to_latin1(&self.keyword).expect("keyword is checked to be Latin-1");
+        data.push(0);
+        data
+    }
+
+    /// Encodes the text.
+    fn text_to_latin1(&self) -> Vec<u8> {
+        string_to_latin1(&self.text).expect("text is checked to be Latin-1")
+    }
+}
+
+impl TypedChunk for TextChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"tEXt";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let (keyword, text) = split_keyword::<Self>(data)?;
+        Self::from_latin1(keyword, text)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = self.keyword_to_latin1();
+        data.extend(self.text_to_latin1());
+        data
+    }
+}
+
+impl ZTxtChunk {
+    /// The only compression method the spec defines, zlib deflate.
+    pub(crate) const COMPRESSION_METHOD: u8 = 0;
+}
+
+impl TypedChunk for ZTxtChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"zTXt";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let (keyword, rest) = split_keyword::<Self>(data)?;
+        let (&method, compressed) = rest
+            .split_first()
+            .ok_or_else(|| Self::invalid("missing compression method"))?;
+        if method != Self::COMPRESSION_METHOD {
+            return Err(Self::invalid(format_args!(
+                "unknown compression method {method}"
+            )));
+        }
+        Self::from_latin1(keyword, &zlib::decompress(compressed)?)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = self.keyword_to_latin1();
+        data.push(Self::COMPRESSION_METHOD);
+        data.extend(zlib::compress(&self.text_to_latin1()));
+        data
+    }
+}
+
+impl<const COMPRESSED: bool> Display for Latin1TextChunk<COMPRESSED> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.keyword, self.text)
+    }
+}
+
+/// An international text chunk (`iTXt`), holding UTF-8 text
+/// along with the language it's in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ITxtChunk {
+    keyword: String,
+    compressed: bool,
+    /// An RFC 3066 language tag like `ja` or `en-GB`, empty if unknown.
+    language_tag: String,
+    /// The keyword translated into the language of the text.
+    translated_keyword: String,
+    text: String,
+}
+
+impl ITxtChunk {
+    /// The only compression method the spec defines, zlib deflate.
+    pub(crate) const COMPRESSION_METHOD: u8 = 0;
+
+    /// Creates an uncompressed international text chunk with
+    /// no language tag or translated keyword.
+    pub(crate) fn new(keyword: &str, text: &str) -> Result<Self, PngError> {
+        check_keyword::<Self>(keyword)?;
+        Ok(Self {
+            keyword: keyword.to_string(),
+            compressed: false,
+            language_tag: String::new(),
+            translated_keyword: String::new(),
+            text: text.to_string(),
+        })
+    }
+
+    /// Sets the language tag and the translated keyword.
+    pub(crate) fn with_language(
+        mut self,
+        language_tag: &str,
+        translated_keyword: &str,
+    ) -> Result<Self, PngError> {
+        check_language_tag(language_tag)?;
+        if translated_keyword.contains('\0') {
+            return Err(Self::invalid("translated keyword has a NUL"));
+        }
+        self.language_tag = language_tag.to_string();
+        self.translated_keyword = translated_keyword.to_string();
+        Ok(self)
+    }
+
+    /// Sets whether the text is stored zlib-compressed.
+    pub(crate) fn with_compression(mut self, compressed: bool) -> Self {
+        self.compressed = compressed;
+        self
+    }
+
+    pub(crate) fn keyword(&self) -> &str {
+        &self.keyword
+    }
+
+    pub(crate) fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    pub(crate) fn language_tag(&self) -> &str {
+        &self.language_tag
+    }
+
+    pub(crate) fn translated_keyword(&self) -> &str {
+        &self.translated_keyword
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Checks that `language_tag` is made of hyphen-separated groups of
+/// 1 to 8 ASCII letters and digits, or is empty.
+fn check_language_tag(language_tag: &str) -> Result<(), PngError> {
+    let valid = language_tag.is_empty()
+        || language_tag.split('-').all(|part| {
+            (1..=8).contains(&part.len()) && part.bytes().all(|byte| byte.is_ascii_alphanumeric())
+        });
+    if !valid {
+        return Err(ITxtChunk::invalid(format_args!(
+            "{language_tag:?} isn't a valid language tag"
+        )));
+    }
+    Ok(())
+}
+
+/// Splits off the bytes up to the next NUL, and the bytes after it.
+fn split_at_nul<'a, T: TypedChunk>(
+    data: &'a [u8],
+    field: &str,
+) -> Result<(&'a [u8], &'a [u8]), PngError> {
+    let separator = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| T::invalid(format_args!("no NUL separator after the {field}")))?;
+    Ok((&data[..separator], &data[separator + 1..]))
+}
+
+impl TypedChunk for ITxtChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"iTXt";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        let (keyword, rest) = split_keyword::<Self>(data)?;
+        let &[flag, method, ref rest @ ..] = rest else {
+            return Err(Self::invalid("missing compression flag or method"));
+        };
+        let compressed = match flag {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(Self::invalid(format_args!(
+                    "invalid compression flag {flag}"
+                )))
+            }
+        };
+        if compressed && method != Self::COMPRESSION_METHOD {
+            return Err(Self::invalid(format_args!(
+                "unknown compression method {method}"
+            )));
+        }
+
+        let (language_tag, rest) = split_at_nul::<Self>(rest, "language tag")?;
+        let language_tag = String::from_utf8(language_tag.to_vec())
+            .map_err(|_| Self::invalid("language tag isn't ASCII"))?;
+        check_language_tag(&language_tag)?;
+        let (translated_keyword, text) = split_at_nul::<Self>(rest, "translated keyword")?;
+        let translated_keyword = String::from_utf8(translated_keyword.to_vec())
+            .map_err(|_| Self::invalid("translated keyword isn't valid UTF-8"))?;
+
+        let text = if compressed {
+            zlib::decompress(text)?
+        } else {
+            text.to_vec()
+        };
+        let text = String::from_utf8(text).map_err(|_| Self::invalid("text isn't valid UTF-8"))?;
+
+        Ok(Self {
+            keyword,
+            compressed,
+            language_tag,
+            translated_keyword,
+            text,
+        })
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = string_to_latin1(&self.keyword).expect("keyword is checked to be Latin-1");
+        data.extend([0, u8::from(self.compressed), Self::COMPRESSION_METHOD]);
+        data.extend_from_slice(self.language_tag.as_bytes());
+        data.push(0);
+        data.extend_from_slice(self.translated_keyword.as_bytes());
+        data.push(0);
+        if self.compressed {
+            data.extend(zlib::compress(self.text.as_bytes()));
+        } else {
+            data.extend_from_slice(self.text.as_bytes());
+        }
+        data
+    }
+}
+
+impl Display for ITxtChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.keyword)?;
+        if !self.language_tag.is_empty() {
+            write!(f, " [{}]", self.language_tag)?;
+        }
+        if !self.translated_keyword.is_empty() {
+            write!(f, " ({})", self.translated_keyword)?;
+        }
+        write!(f, ": {}", self.text)
+    }
+}
+
+/// Which kind of text chunk a `TextEntry` comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextKind {
+    /// `tEXt`
+    Text,
+    /// `zTXt`
+    Compressed,
+    /// `iTXt`
+    International,
+}
+
+impl TextKind {
+    fn of(chunk: &Chunk) -> Option<Self> {
+        match chunk.chunk_type().bytes() {
+            TextChunk::CHUNK_TYPE => Some(Self::Text),
+            ZTxtChunk::CHUNK_TYPE => Some(Self::Compressed),
+            ITxtChunk::CHUNK_TYPE => Some(Self::International),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TextKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Text => "tEXt",
+            Self::Compressed => "zTXt",
+            Self::International => "iTXt",
+        })
+    }
+}
+
+/// A keyword and its value, from any kind of text chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TextEntry {
+    pub(crate) keyword: String,
+    pub(crate) value: String,
+    pub(crate) kind: TextKind,
+    /// Whether the value is stored zlib-compressed.
+    pub(crate) compressed: bool,
+    /// The language of an iTXt value, empty if unknown or not iTXt.
+    pub(crate) language_tag: String,
+    /// The keyword translated into that language, empty if there is none.
+    pub(crate) translated_keyword: String,
+}
+
+impl TextEntry {
+    /// Parses `chunk` if it's a text chunk.
+    fn from_chunk(chunk: &Chunk) -> Option<Result<Self, PngError>> {
+        let kind = TextKind::of(chunk)?;
+        let entry = match kind {
+            TextKind::Text => TextChunk::from_chunk(chunk)
+                .map(|text| Self::latin1(text.keyword(), text.text(), kind)),
+            TextKind::Compressed => ZTxtChunk::from_chunk(chunk)
+                .map(|ztxt| Self::latin1(ztxt.keyword(), ztxt.text(), kind)),
+            TextKind::International => ITxtChunk::from_chunk(chunk).map(|itxt| Self {
+                keyword: itxt.keyword().to_string(),
+                value: itxt.text().to_string(),
+                kind,
+                compressed: itxt.is_compressed(),
+                language_tag: itxt.language_tag().to_string(),
+                translated_keyword: itxt.translated_keyword().to_string(),
+            }),
+        };
+        Some(entry)
+    }
+
+    /// An entry from a tEXt or zTXt chunk, which have no language.
+    fn latin1(keyword: &str, value: &str, kind: TextKind) -> Self {
+        Self {
+            keyword: keyword.to_string(),
+            value: value.to_string(),
+            kind,
+            compressed: kind == TextKind::Compressed,
+            language_tag: String::new(),
+            translated_keyword: String::new(),
+        }
+    }
+}
+
+impl Display for TextEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}", self.keyword, self.kind)?;
+        // zTXt is always compressed, so that only needs saying for iTXt.
+        if self.compressed && self.kind == TextKind::International {
+            write!(f, ", compressed")?;
+        }
+        if !self.language_tag.is_empty() {
+            write!(f, ", {}", self.language_tag)?;
+        }
+        if !self.translated_keyword.is_empty() {
+            write!(f, ", {}", self.translated_keyword)?;
+        }
+        write!(f, "): {}", self.value)
+    }
+}
+
+impl Png {
+    /// Returns the entries of every `tEXt`, `zTXt` and `iTXt` chunk,
+    /// in file order.
+    ///
+    /// Text chunks that fail to parse are left out.
+    pub(crate) fn text_entries(&self) -> Vec<TextEntry> {
+        self.chunks()
+            .iter()
+            .filter_map(TextEntry::from_chunk)
+            .filter_map(Result::ok)
+            .collect()
+    }
+
+    /// Returns the value of the first text entry with the given keyword.
+    pub(crate) fn get_text(&self, keyword: &str) -> Option<String> {
+        self.text_entries()
+            .into_iter()
+            .find(|entry| entry.keyword == keyword)
+            .map(|entry| entry.value)
+    }
+
+    /// Replaces every text entry with the given keyword by a single
+    /// new one, inserted before `IEND`.
+    ///
+    /// Latin-1 values go in `tEXt`, or `zTXt` if they're long.
+    /// Anything else goes in `iTXt`, compressed if it's long.
+    /// Returns the kind of chunk used.
+    pub(crate) fn set_text(&mut self, keyword: &str, value: &str) -> Result<TextKind, PngError> {
+        let is_long = value.len() > COMPRESSION_THRESHOLD;
+        let (chunk, kind) = if check_text::<TextChunk>(value).is_err() {
+            let itxt = ITxtChunk::new(keyword, value)?.with_compression(is_long);
+            (itxt.to_chunk(), TextKind::International)
+        } else if is_long {
+            (
+                ZTxtChunk::new(keyword, value)?.to_chunk(),
+                TextKind::Compressed,
+            )
+        } else {
+            (TextChunk::new(keyword, value)?.to_chunk(), TextKind::Text)
+        };
+        self.replace_text(keyword, chunk);
+        Ok(kind)
+    }
+
+    /// Like `set_text`, but always stores the value in `iTXt`, tagged with
+    /// its language and the keyword translated into that language.
+    /// Either may be empty.
+    pub(crate) fn set_localized_text(
+        &mut self,
+        keyword: &str,
+        value: &str,
+        language_tag: &str,
+        translated_keyword: &str,
+    ) -> Result<(), PngError> {
+        let itxt = ITxtChunk::new(keyword, value)?
+            .with_compression(value.len() > COMPRESSION_THRESHOLD)
+            .with_language(language_tag, translated_keyword)?;
+        self.replace_text(keyword, itxt.to_chunk());
+        Ok(())
+    }
+
+    /// Replaces every text entry with the given keyword by `chunk`.
+    fn replace_text(&mut self, keyword: &str, chunk: Chunk) {
+        // Ignore the error, the keyword may well be new.
+        let _ = self.remove_text(keyword);
+        self.insert_before_iend(chunk);
+    }
+
+    /// Removes every text chunk with the given keyword, even ones that
+    /// fail to parse, and returns how many were removed.
+    pub(crate) fn remove_text(&mut self, keyword: &str) -> Result<usize, PngError> {
+        let keyword = string_to_latin1(keyword);
+        let removed = self.remove_chunks_where(|chunk| {
+            TextKind::of(chunk).is_some()
+                && keyword.as_deref().is_some_and(|keyword| {
+                    chunk.data().split(|&byte| byte == 0).next() == Some(keyword)
+                })
+        });
+        if removed.is_empty() {
+            return Err(PngError::ChunkNotFound);
+        }
+        Ok(removed.len())
+    }
+}
+
+#[cfg(test)]
+mod text_tests {
+    use super::*;
+
+    #[test]
+    fn test_text_chunk_round_trip() {
+        let text = TextChunk::new("Author", "Zoë Müller").unwrap();
+        let chunk = text.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "tEXt");
+        assert_eq!(chunk.data(), b"Author\0Zo\xEB M\xFCller");
+        assert_eq!(TextChunk::from_chunk(&chunk).unwrap(), text);
+    }
+
+    #[test]
+    fn test_text_chunk_decodes_latin1() {
+        let text = TextChunk::from_data(b"Comment\0caf\xE9").unwrap();
+        assert_eq!(text.keyword(), "Comment");
+        assert_eq!(text.text(), "café");
+        assert_eq!(text.to_string(), "Comment: café");
+    }
+
+    #[test]
+    fn test_text_chunk_empty_text() {
+        let text = TextChunk::from_data(b"Title\0").unwrap();
+        assert_eq!(text.text(), "");
+    }
+
+    #[test]
+    fn test_text_chunk_rejects_non_latin1_text() {
+        assert!(TextChunk::new("Title", "日本").is_err());
+        assert!(TextChunk::new("Title", "a\0b").is_err());
+        assert!(TextChunk::from_data(b"Title\0a\0b").is_err());
+    }
+
+    #[test]
+    fn test_text_chunk_missing_separator() {
+        assert!(matches!(
+            TextChunk::from_data(b"Title"),
+            Err(PngError::InvalidChunkData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_keyword_rules() {
+        let long = "k".repeat(80);
+        for keyword in [
+            "",
+            " Title",
+            "Title ",
+            "Ti  tle",
+            "Ti\ttle",
+            long.as_str(),
+            "Tĩtle",
+        ] {
+            assert!(
+                check_keyword::<TextChunk>(keyword).is_err(),
+                "{keyword:?} should be rejected"
+            );
+        }
+        for keyword in ["Title", "Creation Time", "Æsthetics", &long[..79]] {
+            assert!(check_keyword::<TextChunk>(keyword).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_keyword_error_message() {
+        assert_eq!(
+            TextChunk::from_data(b" Title\0text").unwrap_err().to_string(),
+            "error: invalid tEXt chunk: keyword \" Title\" has leading, trailing or consecutive spaces"
+        );
+    }
+
+    #[test]
+    fn test_ztxt_chunk_round_trip() {
+        let xmp = "<x:xmpmeta xmlns:x='adobe:ns:meta/'>café</x:xmpmeta>".repeat(50);
+        let ztxt = ZTxtChunk::new("XML:com.adobe.xmp", &xmp).unwrap();
+        let chunk = ztxt.to_chunk();
+        assert_eq!(chunk.chunk_type().to_string(), "zTXt");
+        assert!(chunk.data().len() < xmp.len() / 10);
+        let parsed = ZTxtChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(parsed, ztxt);
+        assert_eq!(parsed.text(), xmp);
+    }
+
+    #[test]
+    fn test_ztxt_chunk_from_zlib_data() {
+        // "Comment\0", method 0, then zlib.compress(b"caf\xe9 caf\xe9").
+        let mut data = b"Comment\0\0".to_vec();
+        data.extend([
+            0x78, 0x9C, 0x4B, 0x4E, 0x4C, 0x7B, 0xA9, 0x90, 0x0C, 0x24, 0x00, 0x13, 0xD0, 0x04,
+            0x47,
+        ]);
+        let ztxt = ZTxtChunk::from_data(&data).unwrap();
+        assert_eq!(ztxt.keyword(), "Comment");
+        assert_eq!(ztxt.text(), "café café");
+    }
+
+    #[test]
+    fn test_ztxt_chunk_rejects_bad_data() {
+        let valid = ZTxtChunk::new("Comment", "text").unwrap().to_data();
+
+        let mut unknown_method = valid.clone();
+        unknown_method[8] = 1;
+        assert!(ZTxtChunk::from_data(&unknown_method).is_err());
+
+        assert!(matches!(
+            ZTxtChunk::from_data(&valid[..valid.len() - 1]),
+            Err(PngError::InvalidZlibStream(_))
+        ));
+        assert!(ZTxtChunk::from_data(b"Comment\0").is_err());
+    }
+
+    #[test]
+    fn test_itxt_chunk_round_trip() {
+        for compressed in [false, true] {
+            let itxt = ITxtChunk::new("Title", "東京の夜景")
+                .unwrap()
+                .with_language("ja", "タイトル")
+                .unwrap()
+                .with_compression(compressed);
+            let chunk = itxt.to_chunk();
+            assert_eq!(chunk.chunk_type().to_string(), "iTXt");
+            let parsed = ITxtChunk::from_chunk(&chunk).unwrap();
+            assert_eq!(parsed, itxt);
+            assert_eq!(parsed.is_compressed(), compressed);
+        }
+    }
+
+    #[test]
+    fn test_itxt_chunk_layout() {
+        let itxt = ITxtChunk::new("Title", "Grüße")
+            .unwrap()
+            .with_language("de-DE", "Titel")
+            .unwrap();
+        assert_eq!(itxt.to_data(), "Title\0\0\0de-DE\0Titel\0Grüße".as_bytes());
+        assert_eq!(itxt.to_string(), "Title [de-DE] (Titel): Grüße");
+    }
+
+    #[test]
+    fn test_itxt_chunk_without_language() {
+        let itxt = ITxtChunk::from_data(b"Comment\0\0\0\0\0hi").unwrap();
+        assert_eq!(itxt.language_tag(), "");
+        assert_eq!(itxt.translated_keyword(), "");
+        assert_eq!(itxt.text(), "hi");
+        assert_eq!(itxt.to_string(), "Comment: hi");
+    }
+
+    #[test]
+    fn test_itxt_chunk_rejects_bad_data() {
+        for data in [
+            &b"Comment\0\0"[..],
+            b"Comment\0\x02\0\0\0",
+            b"Comment\0\x01\x01\0\0",
+            b"Comment\0\0\0en\0",
+            b"Comment\0\0\0en_US\0\0",
+            b"Comment\0\0\0\0\0\xFF",
+            b"Comment\0\x01\0\0\0not zlib",
+        ] {
+            assert!(ITxtChunk::from_data(data).is_err(), "{data:?} should fail");
+        }
+    }
+
+    #[test]
+    fn test_language_tags() {
+        for tag in ["", "en", "en-GB", "x-klingon", "zh-Hant-TW"] {
+            assert!(check_language_tag(tag).is_ok());
+        }
+        for tag in ["-", "en-", "en_GB", "averyverylongtag", "日本"] {
+            assert!(check_language_tag(tag).is_err());
+        }
+    }
+
+    fn testing_png() -> Png {
+        let mut png = Png::from_chunks(vec![Chunk::new(
+            crate::chunk_type::ChunkType::try_from(*b"IEND").unwrap(),
+            Vec::new(),
+        )]);
+        png.insert_before_iend(TextChunk::new("Title", "Sunset").unwrap().to_chunk());
+        png.insert_before_iend(
+            ZTxtChunk::new("Comment", "long comment")
+                .unwrap()
+                .to_chunk(),
+        );
+        png.insert_before_iend(
+            ITxtChunk::new("Title", "日没")
+                .unwrap()
+                .with_language("ja", "")
+                .unwrap()
+                .to_chunk(),
+        );
+        png
+    }
+
+    #[test]
+    fn test_text_entries() {
+        let entries = testing_png().text_entries();
+        let entries: Vec<(&str, &str, TextKind)> = entries
+            .iter()
+            .map(|entry| (entry.keyword.as_str(), entry.value.as_str(), entry.kind))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("Title", "Sunset", TextKind::Text),
+                ("Comment", "long comment", TextKind::Compressed),
+                ("Title", "日没", TextKind::International),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_entry_display() {
+        let entries = testing_png().text_entries();
+        assert_eq!(entries[0].to_string(), "Title (tEXt): Sunset");
+        assert_eq!(entries[1].to_string(), "Comment (zTXt): long comment");
+        assert_eq!(entries[2].to_string(), "Title (iTXt, ja): 日没");
+    }
+
+    #[test]
+    fn test_set_localized_text() {
+        let mut png = testing_png();
+        png.set_localized_text("Title", "Titel", "de", "Titel")
+            .unwrap();
+        let entries = png.text_entries();
+        let title = entries.last().unwrap();
+        assert_eq!(title.to_string(), "Title (iTXt, de, Titel): Titel");
+        assert_eq!(
+            entries
+                .iter()
+                .filter(|entry| entry.keyword == "Title")
+                .count(),
+            1
+        );
+        assert!(png
+            .set_localized_text("Title", "Titel", "de_DE", "")
+            .is_err());
+    }
+
+    #[test]
+    fn test_text_entries_skip_broken_chunks() {
+        let mut png = testing_png();
+        png.insert_before_iend(Chunk::new(
+            crate::chunk_type::ChunkType::try_from(*b"zTXt").unwrap(),
+            b"Broken\0\0not zlib".to_vec(),
+        ));
+        assert_eq!(png.text_entries().len(), 3);
+    }
+
+    #[test]
+    fn test_get_text() {
+        let png = testing_png();
+        assert_eq!(png.get_text("Title").as_deref(), Some("Sunset"));
+        assert_eq!(png.get_text("Comment").as_deref(), Some("long comment"));
+        assert!(png.get_text("Author").is_none());
+    }
+
+    #[test]
+    fn test_set_text_picks_chunk_type() {
+        let mut png = testing_png();
+        assert_eq!(png.set_text("Author", "Zoë").unwrap(), TextKind::Text);
+        assert_eq!(
+            png.set_text("Description", &"a".repeat(2000)).unwrap(),
+            TextKind::Compressed
+        );
+        assert_eq!(
+            png.set_text("Title", "夕日").unwrap(),
+            TextKind::International
+        );
+
+        assert_eq!(png.get_text("Author").as_deref(), Some("Zoë"));
+        assert_eq!(png.get_text("Title").as_deref(), Some("夕日"));
+        assert_eq!(
+            png.text_entries()
+                .iter()
+                .filter(|entry| entry.keyword == "Title")
+                .count(),
+            1
+        );
+        let last = png.chunks().last().unwrap();
+        assert_eq!(last.chunk_type().to_string(), "IEND");
+    }
+
+    #[test]
+    fn test_set_text_rejects_bad_keyword() {
+        let mut png = testing_png();
+        assert!(png.set_text(" Title", "x").is_err());
+        // Nothing was removed.
+        assert_eq!(png.get_text("Title").as_deref(), Some("Sunset"));
+    }
+
+    #[test]
+    fn test_remove_text() {
+        let mut png = testing_png();
+        assert_eq!(png.remove_text("Title").unwrap(), 2);
+        assert!(png.get_text("Title").is_none());
+        assert!(matches!(
+            png.remove_text("Title"),
+            Err(PngError::ChunkNotFound)
+        ));
+        assert_eq!(png.text_entries().len(), 1);
+    }
+
+    #[test]
+    fn test_latin1_conversions() {
+        assert_eq!(latin1_to_string(&[0x41, 0xE9, 0xFF]), "Aéÿ");
+        assert_eq!(string_to_latin1("Aéÿ").unwrap(), [0x41, 0xE9, 0xFF]);
+        assert!(string_to_latin1("€").is_none());
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{errors::PngError, png::Png, typed_chunk::TypedChunk};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// The time of the last modification of the image (`tIME`), in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TimeChunk {
+    pub(crate) year: u16,
+    /// 1 to 12.
+    pub(crate) month: u8,
+    /// 1 to 31, depending on the month.
+    pub(crate) day: u8,
+    /// 0 to 23.
+    pub(crate) hour: u8,
+    /// 0 to 59.
+    pub(crate) minute: u8,
+    /// 0 to 60, to allow for leap seconds.
+    pub(crate) second: u8,
+}
+
+impl TimeChunk {
+    /// The length of the tIME chunk data.
+    pub(crate) const LENGTH: usize = 7;
+
+    /// Returns the current time.
+    pub(crate) fn now() -> Result<Self, PngError> {
+        Self::try_from(SystemTime::now())
+    }
+
+    /// Checks every field against its range.
+    pub(crate) fn validate(&self) -> Result<(), PngError> {
+        let days_in_month = days_in_month(i64::from(self.year), self.month);
+        let fields = [
+            ("month", self.month, 1, 12),
+            ("day", self.day, 1, days_in_month),
+            ("hour", self.hour, 0, 23),
+            ("minute", self.minute, 0, 59),
+            ("second", self.second, 0, 60),
+        ];
+        for (name, value, min, max) in fields {
+            if !(min..=max).contains(&value) {
+                return Err(Self::invalid(format_args!(
+                    "{name} must be between {min} and {max}, got {value}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/// The number of days in `month`, or 0 if it's not a month.
+fn days_in_month(year: i64, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Counts the days from 1970-01-01 to the given date, which may be negative.
+// Howard Hinnant's `days_from_civil`, which treats March as the first month
+// so the leap day falls at the end of the year.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((i64::from(month) + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u8;
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+impl TryFrom<SystemTime> for TimeChunk {
+    type Error = PngError;
+
+    /// Converts to UTC, failing only for years that don't fit the chunk.
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(error) => {
+                // Round down, so a time just before the epoch is still in 1969.
+                let duration = error.duration();
+                -(duration.as_secs() as i64) - i64::from(duration.subsec_nanos() > 0)
+            }
+        };
+        let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+        let seconds_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+        Ok(Self {
+            year: u16::try_from(year)
+                .map_err(|_| Self::invalid(format_args!("year {year} is out of range")))?,
+            month,
+            day,
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day / 60 % 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+        })
+    }
+}
+
+impl From<TimeChunk> for SystemTime {
+    /// A leap second comes out as the first second of the next minute.
+    fn from(time: TimeChunk) -> Self {
+        let days = days_from_civil(i64::from(time.year), time.month, time.day);
+        let seconds = days * SECONDS_PER_DAY
+            + i64::from(time.hour) * 3600
+            + i64::from(time.minute) * 60
+            + i64::from(time.second);
+        let duration = Duration::from_secs(seconds.unsigned_abs());
+        if seconds >= 0 {
+            UNIX_EPOCH + duration
+        } else {
+            UNIX_EPOCH - duration
+        }
+    }
+}
+
+impl TypedChunk for TimeChunk {
+    const CHUNK_TYPE: [u8; 4] = *b"tIME";
+
+    fn from_data(data: &[u8]) -> Result<Self, PngError> {
+        Self::check_length(data, Self::LENGTH)?;
+        let time = Self {
+            year: u16::from_be_bytes([data[0], data[1]]),
+            month: data[2],
+            day: data[3],
+            hour: data[4],
+            minute: data[5],
+            second: data[6],
+        };
+        time.validate()?;
+        Ok(time)
+    }
+
+    fn to_data(&self) -> Vec<u8> {
+        let mut data = self.year.to_be_bytes().to_vec();
+        data.extend([self.month, self.day, self.hour, self.minute, self.second]);
+        data
+    }
+}
+
+impl Display for TimeChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl Png {
+    /// Replaces any tIME chunks with one holding `time`, before `IEND`.
+    pub(crate) fn set_time(&mut self, time: TimeChunk) {
+        self.remove_chunks_where(|chunk| chunk.chunk_type().bytes() == TimeChunk::CHUNK_TYPE);
+        self.insert_before_iend(time.to_chunk());
+    }
+
+    /// Sets tIME to the current time, to mark the image as modified.
+    pub(crate) fn touch(&mut self) -> Result<(), PngError> {
+        self.set_time(TimeChunk::now()?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod time_tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{chunk::Chunk, chunk_type::ChunkType};
+
+    fn time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> TimeChunk {
+        TimeChunk {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    fn seconds(seconds: i64) -> SystemTime {
+        let duration = Duration::from_secs(seconds.unsigned_abs());
+        if seconds >= 0 {
+            UNIX_EPOCH + duration
+        } else {
+            UNIX_EPOCH - duration
+        }
+    }
+
+    #[test]
+    fn test_time_chunk_round_trip() {
+        let time = time(2024, 2, 29, 23, 59, 60);
+        let chunk = time.to_chunk();
+        assert_eq!(chunk.data(), [0x07, 0xE8, 2, 29, 23, 59, 60]);
+        assert_eq!(TimeChunk::from_chunk(&chunk).unwrap(), time);
+    }
+
+    #[test]
+    fn test_time_chunk_range_checks() {
+        for invalid in [
+            time(2024, 0, 1, 0, 0, 0),
+            time(2024, 13, 1, 0, 0, 0),
+            time(2023, 2, 29, 0, 0, 0),
+            time(2100, 2, 29, 0, 0, 0),
+            time(2024, 4, 31, 0, 0, 0),
+            time(2024, 1, 1, 24, 0, 0),
+            time(2024, 1, 1, 0, 60, 0),
+            time(2024, 1, 1, 0, 0, 61),
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?} should be invalid");
+            assert!(TimeChunk::from_data(&invalid.to_data()).is_err());
+        }
+        assert!(time(2000, 2, 29, 0, 0, 0).validate().is_ok());
+    }
+
+    #[test]
+    fn test_time_chunk_wrong_length() {
+        assert!(matches!(
+            TimeChunk::from_data(&[0x07, 0xE8, 1, 1, 0, 0]),
+            Err(PngError::InvalidChunkData { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_system_time() {
+        for (seconds_since_epoch, expected) in [
+            (0, time(1970, 1, 1, 0, 0, 0)),
+            (951_827_696, time(2000, 2, 29, 12, 34, 56)),
+            (4_107_542_400, time(2100, 3, 1, 0, 0, 0)),
+            (-1, time(1969, 12, 31, 23, 59, 59)),
+        ] {
+            let time = TimeChunk::try_from(seconds(seconds_since_epoch)).unwrap();
+            assert_eq!(time, expected);
+            assert_eq!(SystemTime::from(time), seconds(seconds_since_epoch));
+        }
+    }
+
+    #[test]
+    fn test_from_system_time_rounds_down() {
+        let just_before_epoch = UNIX_EPOCH - Duration::from_millis(1);
+        assert_eq!(
+            TimeChunk::try_from(just_before_epoch).unwrap(),
+            time(1969, 12, 31, 23, 59, 59)
+        );
+    }
+
+    #[test]
+    fn test_leap_second_to_system_time() {
+        let leap = time(2016, 12, 31, 23, 59, 60);
+        assert_eq!(
+            SystemTime::from(leap),
+            SystemTime::from(time(2017, 1, 1, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_time_chunk_display() {
+        assert_eq!(
+            time(2024, 5, 1, 8, 3, 9).to_string(),
+            "2024-05-01 08:03:09 UTC"
+        );
+    }
+
+    #[test]
+    fn test_png_set_time() {
+        let iend = Chunk::new(ChunkType::from_str("IEND").unwrap(), Vec::new());
+        let mut png = Png::from_chunks(vec![iend]);
+        assert!(png.find::<TimeChunk>().is_none());
+
+        png.set_time(time(2020, 1, 1, 0, 0, 0));
+        // tIME only has whole seconds, so the lower bound is truncated too.
+        let before = SystemTime::from(TimeChunk::try_from(SystemTime::now()).unwrap());
+        png.touch().unwrap();
+        let after = SystemTime::now();
+        assert_eq!(png.chunks().len(), 2);
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "IEND");
+        let touched = SystemTime::from(png.find::<TimeChunk>().unwrap().unwrap());
+        assert!(before <= touched && touched <= after);
+    }
+}
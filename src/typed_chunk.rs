@@ -0,0 +1,152 @@
+use std::fmt::Display;
+
+use crate::{chunk::Chunk, chunk_type::ChunkType, errors::PngError, ihdr::Ihdr, png::Png};
+
+/// A chunk whose data layout is defined by the PNG spec.
+///
+/// Implementors only say how to parse and encode the chunk data;
+/// converting to and from a `Chunk` comes for free.
+pub(crate) trait TypedChunk: Sized {
+    /// The chunk type code, e.g. `*b"IHDR"`.
+    const CHUNK_TYPE: [u8; 4];
+
+    /// Parses and validates the chunk data.
+    fn from_data(data: &[u8]) -> Result<Self, PngError>;
+
+    /// Encodes the chunk data.
+    fn to_data(&self) -> Vec<u8>;
+
+    /// Parses `chunk`, checking that it has the right chunk type.
+    fn from_chunk(chunk: &Chunk) -> Result<Self, PngError> {
+        check_chunk_type(chunk, Self::CHUNK_TYPE)?;
+        Self::from_data(chunk.data())
+    }
+
+    /// Builds a `Chunk` holding the encoded data.
+    fn to_chunk(&self) -> Chunk {
+        Chunk::new(Self::chunk_type(), self.to_data())
+    }
+
+    /// Returns `CHUNK_TYPE` as a `ChunkType`.
+    fn chunk_type() -> ChunkType {
+        ChunkType::try_from(Self::CHUNK_TYPE).expect("typed chunks have valid chunk types")
+    }
+
+    /// Fails unless `data` is exactly `length` bytes long.
+    fn check_length(data: &[u8], length: usize) -> Result<(), PngError> {
+        check_length(Self::CHUNK_TYPE, data, length)
+    }
+
+    /// An `InvalidChunkData` error for this chunk type.
+    fn invalid(reason: impl Display) -> PngError {
+        invalid(Self::CHUNK_TYPE, reason)
+    }
+}
+
+/// Like `TypedChunk`, for chunks whose data layout depends on
+/// the image header, such as tRNS and bKGD.
+pub(crate) trait DependentChunk: Sized {
+    /// The chunk type code, e.g. `*b"tRNS"`.
+    const CHUNK_TYPE: [u8; 4];
+
+    /// Parses the chunk data and validates it against `ihdr`.
+    fn from_data(data: &[u8], ihdr: &Ihdr) -> Result<Self, PngError>;
+
+    /// Encodes the chunk data.
+    fn to_data(&self) -> Vec<u8>;
+
+    /// Parses `chunk`, checking that it has the right chunk type.
+    fn from_chunk(chunk: &Chunk, ihdr: &Ihdr) -> Result<Self, PngError> {
+        check_chunk_type(chunk, Self::CHUNK_TYPE)?;
+        Self::from_data(chunk.data(), ihdr)
+    }
+
+    /// Builds a `Chunk` holding the encoded data.
+    fn to_chunk(&self) -> Chunk {
+        let chunk_type =
+            ChunkType::try_from(Self::CHUNK_TYPE).expect("typed chunks have valid chunk types");
+        Chunk::new(chunk_type, self.to_data())
+    }
+
+    /// Fails unless `data` is exactly `length` bytes long.
+    fn check_length(data: &[u8], length: usize) -> Result<(), PngError> {
+        check_length(Self::CHUNK_TYPE, data, length)
+    }
+
+    /// An `InvalidChunkData` error for this chunk type.
+    fn invalid(reason: impl Display) -> PngError {
+        invalid(Self::CHUNK_TYPE, reason)
+    }
+}
+
+fn check_chunk_type(chunk: &Chunk, expected: [u8; 4]) -> Result<(), PngError> {
+    if chunk.chunk_type().bytes() != expected {
+        return Err(PngError::UnexpectedChunkType {
+            expected: expected.escape_ascii().to_string(),
+            got: chunk.chunk_type().to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_length(chunk_type: [u8; 4], data: &[u8], length: usize) -> Result<(), PngError> {
+    if data.len() != length {
+        return Err(invalid(
+            chunk_type,
+            format_args!("length must be {length}, got {}", data.len()),
+        ));
+    }
+    Ok(())
+}
+
+fn invalid(chunk_type: [u8; 4], reason: impl Display) -> PngError {
+    PngError::InvalidChunkData {
+        chunk_type: chunk_type.escape_ascii().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Checks that every 2-byte sample in `data` fits in `bit_depth` bits,
+/// as the spec requires of tRNS and bKGD colors.
+pub(crate) fn check_samples<T: DependentChunk>(data: &[u8], bit_depth: u8) -> Result<(), PngError> {
+    let too_big = (0..data.len())
+        .step_by(2)
+        .map(|offset| be_u16(data, offset))
+        .find(|&sample| u32::from(sample) >> bit_depth != 0);
+    match too_big {
+        Some(sample) => Err(T::invalid(format_args!(
+            "sample {sample} doesn't fit in {bit_depth} bits"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Reads the big-endian `u16` at `offset`, which must be in bounds.
+pub(crate) fn be_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/// Reads the big-endian `u32` at `offset`, which must be in bounds.
+pub(crate) fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+impl Png {
+    /// Parses the first chunk of type `T`, if there is one.
+    pub(crate) fn find<T: TypedChunk>(&self) -> Option<Result<T, PngError>> {
+        self.chunks()
+            .iter()
+            .find(|chunk| chunk.chunk_type().bytes() == T::CHUNK_TYPE)
+            .map(T::from_chunk)
+    }
+
+    /// Parses the first chunk of type `T` against the image header,
+    /// if there is one. Fails if the header is missing or invalid.
+    pub(crate) fn find_dependent<T: DependentChunk>(&self) -> Option<Result<T, PngError>> {
+        let chunk = self
+            .chunks()
+            .iter()
+            .find(|chunk| chunk.chunk_type().bytes() == T::CHUNK_TYPE)?;
+        Some(self.ihdr().and_then(|ihdr| T::from_chunk(chunk, &ihdr)))
+    }
+}
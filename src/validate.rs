@@ -0,0 +1,287 @@
+use std::fmt::Display;
+
+use crate::{
+    errors::{ChunkContext, PngError},
+    ihdr::ColorType,
+    png::Png,
+};
+
+/// Chunk types that may appear at most once in a PNG.
+const ONE_ONLY_CHUNKS: [[u8; 4]; 14] = [
+    *b"IHDR", *b"PLTE", *b"IEND", *b"gAMA", *b"cHRM", *b"sRGB", *b"iCCP", *b"pHYs", *b"tIME",
+    *b"sBIT", *b"bKGD", *b"hIST", *b"tRNS", *b"eXIf",
+];
+
+/// A way in which a PNG breaks the chunk rules of the spec.
+#[derive(Debug)]
+pub(crate) enum Violation {
+    /// The first chunk isn't IHDR.
+    IhdrNotFirst,
+    /// The IHDR chunk doesn't parse or breaks the spec.
+    InvalidIhdr {
+        context: ChunkContext,
+        error: PngError,
+    },
+    /// There is no IEND chunk.
+    MissingIend,
+    /// A chunk comes after IEND.
+    ChunkAfterIend { context: ChunkContext },
+    /// A chunk that may only appear once appears again.
+    DuplicateChunk { context: ChunkContext },
+    /// There is no IDAT chunk.
+    MissingIdat,
+    /// An IDAT chunk is separated from the previous IDAT chunk.
+    IdatNotConsecutive { context: ChunkContext },
+    /// PLTE comes after the first IDAT chunk.
+    PlteAfterIdat { context: ChunkContext },
+    /// The color type is indexed but there is no PLTE chunk.
+    MissingPlte,
+    /// The color type is grayscale, which doesn't allow a PLTE chunk.
+    UnexpectedPlte { context: ChunkContext },
+    /// The chunk type's reserved bit is set.
+    InvalidReservedBit { context: ChunkContext },
+}
+
+impl Violation {
+    /// A short, stable name for the kind of violation.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::IhdrNotFirst => "ihdr-not-first",
+            Self::InvalidIhdr { .. } => "invalid-ihdr",
+            Self::MissingIend => "missing-iend",
+            Self::ChunkAfterIend { .. } => "chunk-after-iend",
+            Self::DuplicateChunk { .. } => "duplicate-chunk",
+            Self::MissingIdat => "missing-idat",
+            Self::IdatNotConsecutive { .. } => "idat-not-consecutive",
+            Self::PlteAfterIdat { .. } => "plte-after-idat",
+            Self::MissingPlte => "missing-plte",
+            Self::UnexpectedPlte { .. } => "unexpected-plte",
+            Self::InvalidReservedBit { .. } => "invalid-reserved-bit",
+        }
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IhdrNotFirst => write!(f, "the first chunk is not IHDR"),
+            Self::InvalidIhdr { context, error } => write!(f, "{context}: {error}"),
+            Self::MissingIend => write!(f, "there is no IEND chunk"),
+            Self::ChunkAfterIend { context } => write!(f, "{context}: chunk after IEND"),
+            Self::DuplicateChunk { context } => {
+                write!(f, "{context}: chunk may only appear once")
+            }
+            Self::MissingIdat => write!(f, "there is no IDAT chunk"),
+            Self::IdatNotConsecutive { context } => {
+                write!(f, "{context}: IDAT chunks must be consecutive")
+            }
+            Self::PlteAfterIdat { context } => write!(f, "{context}: PLTE must come before IDAT"),
+            Self::MissingPlte => write!(f, "indexed images need a PLTE chunk"),
+            Self::UnexpectedPlte { context } => {
+                write!(f, "{context}: grayscale images must not have a PLTE chunk")
+            }
+            Self::InvalidReservedBit { context } => {
+                write!(f, "{context}: the reserved bit of the chunk type is set")
+            }
+        }
+    }
+}
+
+impl Png {
+    /// Checks the order and number of chunks against the PNG spec.
+    ///
+    /// Unlike parsing, which stops at the first problem, this
+    /// returns every violation found, in file order.
+    pub(crate) fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let ihdr = match self.chunks().first() {
+            Some(chunk) if chunk.chunk_type().bytes() == *b"IHDR" => self.ihdr().map_err(|error| {
+                violations.push(Violation::InvalidIhdr {
+                    context: ChunkContext::new(0, Some(*b"IHDR"), 8),
+                    error,
+                });
+            }),
+            _ => {
+                violations.push(Violation::IhdrNotFirst);
+                Err(())
+            }
+        };
+
+        let mut seen: Vec<[u8; 4]> = Vec::new();
+        let mut seen_iend = false;
+        let mut last_idat: Option<usize> = None;
+        for ((index, chunk), offset) in self.chunks().iter().enumerate().zip(self.chunk_offsets()) {
+            let chunk_type = chunk.chunk_type();
+            let bytes = chunk_type.bytes();
+            let context = || ChunkContext::new(index, Some(bytes), offset);
+
+            if !chunk_type.is_reserved_bit_valid() {
+                violations.push(Violation::InvalidReservedBit { context: context() });
+            }
+            if seen_iend {
+                violations.push(Violation::ChunkAfterIend { context: context() });
+            }
+            if ONE_ONLY_CHUNKS.contains(&bytes) && seen.contains(&bytes) {
+                violations.push(Violation::DuplicateChunk { context: context() });
+            }
+            match &bytes {
+                b"IEND" => seen_iend = true,
+                b"IDAT" => {
+                    if last_idat.is_some_and(|last| last + 1 != index) {
+                        violations.push(Violation::IdatNotConsecutive { context: context() });
+                    }
+                    last_idat = Some(index);
+                }
+                b"PLTE" => {
+                    if last_idat.is_some() {
+                        violations.push(Violation::PlteAfterIdat { context: context() });
+                    }
+                    if ihdr.is_ok_and(|ihdr| {
+                        matches!(
+                            ihdr.color_type,
+                            ColorType::Grayscale | ColorType::GrayscaleAlpha
+                        )
+                    }) {
+                        violations.push(Violation::UnexpectedPlte { context: context() });
+                    }
+                }
+                _ => {}
+            }
+            seen.push(bytes);
+        }
+
+        if last_idat.is_none() {
+            violations.push(Violation::MissingIdat);
+        }
+        if ihdr.is_ok_and(|ihdr| ihdr.color_type == ColorType::Indexed) && !seen.contains(b"PLTE") {
+            violations.push(Violation::MissingPlte);
+        }
+        if !seen_iend {
+            violations.push(Violation::MissingIend);
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use super::*;
+    use crate::{
+        test_utils::{chunk, chunk_with_data, ihdr},
+        typed_chunk::TypedChunk,
+    };
+
+    fn testing_png(color_type: ColorType, chunk_types: &[&str]) -> Png {
+        let mut chunks = vec![ihdr(color_type, 8).to_chunk()];
+        chunks.extend(chunk_types.iter().map(|chunk_type| chunk(chunk_type)));
+        Png::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_validate_valid_png() {
+        let rgb = testing_png(ColorType::Rgb, &["gAMA", "IDAT", "IDAT", "tEXt", "IEND"]);
+        assert!(rgb.validate().is_empty());
+        let indexed = testing_png(ColorType::Indexed, &["PLTE", "IDAT", "IEND"]);
+        assert!(indexed.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_ihdr_not_first() {
+        let png = Png::from_chunks(vec![
+            chunk("IDAT"),
+            ihdr(ColorType::Rgb, 8).to_chunk(),
+            chunk("IEND"),
+        ]);
+        assert!(matches!(png.validate()[..], [Violation::IhdrNotFirst]));
+    }
+
+    #[test]
+    fn test_validate_invalid_ihdr() {
+        let zeroed_ihdr = chunk_with_data("IHDR", &[0; 13]);
+        let png = Png::from_chunks(vec![zeroed_ihdr, chunk("IDAT"), chunk("IEND")]);
+        let violations = png.validate();
+        assert!(matches!(violations[..], [Violation::InvalidIhdr { .. }]));
+    }
+
+    #[test]
+    fn test_validate_iend() {
+        let violations = testing_png(ColorType::Rgb, &["IDAT"]).validate();
+        assert!(matches!(violations[..], [Violation::MissingIend]));
+
+        let violations = testing_png(ColorType::Rgb, &["IDAT", "IEND", "tEXt", "IEND"]).validate();
+        assert!(matches!(
+            violations[..],
+            [
+                Violation::ChunkAfterIend { .. },
+                Violation::ChunkAfterIend { .. },
+                Violation::DuplicateChunk { .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_validate_idat() {
+        let violations = testing_png(ColorType::Rgb, &["IEND"]).validate();
+        assert!(matches!(violations[..], [Violation::MissingIdat]));
+
+        let violations = testing_png(ColorType::Rgb, &["IDAT", "tEXt", "IDAT", "IEND"]).validate();
+        assert!(matches!(
+            violations[..],
+            [Violation::IdatNotConsecutive { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_validate_plte() {
+        let violations = testing_png(ColorType::Indexed, &["IDAT", "IEND"]).validate();
+        assert!(matches!(violations[..], [Violation::MissingPlte]));
+
+        let violations = testing_png(ColorType::Indexed, &["IDAT", "PLTE", "IEND"]).validate();
+        assert!(matches!(violations[..], [Violation::PlteAfterIdat { .. }]));
+
+        let violations = testing_png(ColorType::Grayscale, &["PLTE", "IDAT", "IEND"]).validate();
+        assert!(matches!(violations[..], [Violation::UnexpectedPlte { .. }]));
+    }
+
+    #[test]
+    fn test_validate_duplicate_chunks() {
+        let violations = testing_png(
+            ColorType::Rgb,
+            &[
+                "gAMA", "gAMA", "tIME", "tIME", "tEXt", "tEXt", "IDAT", "IEND",
+            ],
+        )
+        .validate();
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .all(|violation| matches!(violation, Violation::DuplicateChunk { .. })));
+    }
+
+    #[test]
+    fn test_validate_reserved_bit() {
+        let violations = testing_png(ColorType::Rgb, &["IDAT", "ruse", "IEND"]).validate();
+        assert!(matches!(
+            violations[..],
+            [Violation::InvalidReservedBit { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_validate_reports_every_violation() {
+        let png = Png::from_chunks(vec![chunk("IDAT"), chunk("ruse"), chunk("IDAT")]);
+        let violations = png.validate();
+        assert_eq!(violations.len(), 4);
+    }
+
+    #[test]
+    fn test_violation_display() {
+        let violations = testing_png(ColorType::Rgb, &["IDAT", "gAMA", "IDAT", "IEND"]).validate();
+        // IHDR is 25 bytes long and every other chunk 12, so the
+        // second IDAT chunk starts at 8 + 25 + 12 + 12.
+        assert_eq!(
+            violations[0].to_string(),
+            "chunk #3 'IDAT' at offset 0x39: IDAT chunks must be consecutive"
+        );
+    }
+}
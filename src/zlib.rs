@@ -0,0 +1,579 @@
+//! A small zlib (RFC 1950) and DEFLATE (RFC 1951) implementation,
+//! enough for the compressed text and profile chunks.
+//!
+//! Inflating handles every block type. Deflating uses LZ77 with the
+//! fixed Huffman codes, which compresses text well without having to
+//! build code tables.
+
+use crate::errors::PngError;
+
+/// The most bytes `decompress` will produce, so a tiny chunk
+/// can't expand into gigabytes.
+const MAX_DECOMPRESSED_LENGTH: usize = 1 << 28;
+
+/// The base length and extra bits of length codes 257 to 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The base distance and extra bits of distance codes 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which code length code lengths are stored.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const WINDOW_SIZE: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same hash to try for each match.
+const MAX_CHAIN: usize = 128;
+const HASH_BITS: u32 = 15;
+
+fn invalid(reason: &'static str) -> PngError {
+    PngError::InvalidZlibStream(reason)
+}
+
+/// Computes the Adler-32 checksum zlib streams end with.
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    // 5552 is the most bytes that can be summed before `b` can overflow.
+    let (mut a, mut b) = (1u32, 0u32);
+    for block in data.chunks(5552) {
+        for &byte in block {
+            a += u32::from(byte);
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    (b << 16) | a
+}
+
+/// Reads bits least significant first, as DEFLATE packs them.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            bit_buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, PngError> {
+        while self.bit_count < count {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or(invalid("unexpected end of data"))?;
+            self.position += 1;
+            self.bit_buffer |= u32::from(byte) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u32 << count) - 1);
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align_to_byte(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], PngError> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or(invalid("unexpected end of data"))?;
+        self.position += count;
+        Ok(bytes)
+    }
+}
+
+/// A canonical Huffman code, decoded one bit at a time.
+struct Huffman {
+    /// How many codes there are of each length.
+    counts: [u16; 16],
+    /// The symbols, ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, PngError> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+
+        // Make sure no more codes are used than fit in the code space.
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[usize::from(offsets[usize::from(length)])] = symbol as u16;
+                offsets[usize::from(length)] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, PngError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+/// Decompresses a zlib stream, checking its header and Adler-32 checksum.
+pub(crate) fn decompress(data: &[u8]) -> Result<Vec<u8>, PngError> {
+    let &[cmf, flg, ..] = data else {
+        return Err(invalid("missing header"));
+    };
+    if cmf & 0x0F != 8 || cmf >> 4 > 7 {
+        return Err(invalid("compression method isn't deflate"));
+    }
+    if (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+        return Err(invalid("header check failed"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid("preset dictionaries aren't supported"));
+    }
+
+    let mut reader = BitReader::new(&data[2..]);
+    let output = inflate(&mut reader)?;
+
+    let trailer = reader.bytes(4)?;
+    if u32::from_be_bytes(trailer.try_into().expect("4 bytes")) != adler32(&output) {
+        return Err(invalid("Adler-32 checksum mismatch"));
+    }
+    Ok(output)
+}
+
+fn inflate(reader: &mut BitReader) -> Result<Vec<u8>, PngError> {
+    let mut output = Vec::new();
+    loop {
+        let is_final = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let header = reader.bytes(4)?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                let complement = u16::from_le_bytes([header[2], header[3]]);
+                if length != !complement {
+                    return Err(invalid("stored block length check failed"));
+                }
+                output.extend_from_slice(reader.bytes(usize::from(length))?);
+                if output.len() > MAX_DECOMPRESSED_LENGTH {
+                    return Err(invalid("decompressed data is too large"));
+                }
+            }
+            1 => {
+                let (literals, distances) = fixed_codes()?;
+                inflate_block(reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(reader)?;
+                inflate_block(reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("invalid block type")),
+        }
+        if is_final {
+            return Ok(output);
+        }
+    }
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), PngError> {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), PngError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(invalid("too many length or distance codes"));
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (length, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *index
+                    .checked_sub(1)
+                    .and_then(|previous| lengths.get(previous))
+                    .ok_or(invalid("repeated length with no previous length"))?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        let run = lengths
+            .get_mut(index..index + repeat)
+            .ok_or(invalid("too many code lengths"))?;
+        run.fill(length);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(invalid("missing end-of-block code"));
+    }
+
+    let (literal_lengths, distance_lengths) = lengths.split_at(literal_count);
+    Ok((
+        Huffman::new(literal_lengths)?,
+        Huffman::new(distance_lengths)?,
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), PngError> {
+    loop {
+        let symbol = usize::from(literals.decode(reader)?);
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err(invalid("invalid length code"));
+                }
+                let length = usize::from(LENGTH_BASE[index])
+                    + reader.bits(u32::from(LENGTH_EXTRA[index]))? as usize;
+
+                let index = usize::from(distances.decode(reader)?);
+                if index >= DISTANCE_BASE.len() {
+                    return Err(invalid("invalid distance code"));
+                }
+                let distance = usize::from(DISTANCE_BASE[index])
+                    + reader.bits(u32::from(DISTANCE_EXTRA[index]))? as usize;
+                if distance > output.len() {
+                    return Err(invalid("distance reaches before the start of the data"));
+                }
+
+                // The match may overlap the bytes it produces, so copy one at a time.
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+            }
+        }
+        if output.len() > MAX_DECOMPRESSED_LENGTH {
+            return Err(invalid("decompressed data is too large"));
+        }
+    }
+}
+
+/// Writes bits least significant first, as DEFLATE packs them.
+struct BitWriter {
+    output: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= u64::from(value) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which is packed most significant bit first.
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    fn literal(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn copy(&mut self, length: usize, distance: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|&base| usize::from(base) <= length)
+            .expect("length is at least 3");
+        self.literal(257 + index as u16);
+        self.bits(
+            (length - usize::from(LENGTH_BASE[index])) as u32,
+            u32::from(LENGTH_EXTRA[index]),
+        );
+
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|&base| usize::from(base) <= distance)
+            .expect("distance is at least 1");
+        self.code(index as u32, 5);
+        self.bits(
+            (distance - usize::from(DISTANCE_BASE[index])) as u32,
+            u32::from(DISTANCE_EXTRA[index]),
+        );
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        self.output
+    }
+}
+
+fn hash(data: &[u8]) -> usize {
+    let value = u32::from(data[0]) << 16 | u32::from(data[1]) << 8 | u32::from(data[2]);
+    (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compresses `data` into a zlib stream.
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        output: vec![0x78, 0x9C],
+        bit_buffer: 0,
+        bit_count: 0,
+    };
+    // A single final block using the fixed Huffman codes.
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    // `head` holds the latest position for each hash, `previous` the
+    // position before it with the same hash, both offset by one so
+    // zero means none.
+    let mut head = vec![0usize; 1 << HASH_BITS];
+    let mut previous = vec![0usize; WINDOW_SIZE];
+    let mut position = 0;
+    while position < data.len() {
+        let (length, distance) = longest_match(data, position, &head, &previous);
+        if length >= MIN_MATCH {
+            writer.copy(length, distance);
+            for position in position..position + length {
+                insert(data, position, &mut head, &mut previous);
+            }
+            position += length;
+        } else {
+            writer.literal(u16::from(data[position]));
+            insert(data, position, &mut head, &mut previous);
+            position += 1;
+        }
+    }
+    writer.literal(256);
+
+    let mut output = writer.finish();
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    output
+}
+
+/// Records `position` as the latest position for the hash of its next
+/// three bytes.
+fn insert(data: &[u8], position: usize, head: &mut [usize], previous: &mut [usize]) {
+    if position + MIN_MATCH <= data.len() {
+        let hash = hash(&data[position..]);
+        previous[position % WINDOW_SIZE] = head[hash];
+        head[hash] = position + 1;
+    }
+}
+
+/// Finds the longest earlier match for the bytes at `position`,
+/// returning its length and distance.
+fn longest_match(
+    data: &[u8],
+    position: usize,
+    head: &[usize],
+    previous: &[usize],
+) -> (usize, usize) {
+    if position + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+    let max_length = MAX_MATCH.min(data.len() - position);
+    let (mut best_length, mut best_distance) = (0, 0);
+    let mut candidate = head[hash(&data[position..])];
+    for _ in 0..MAX_CHAIN {
+        let Some(start) = candidate.checked_sub(1) else {
+            break;
+        };
+        let distance = position - start;
+        if distance > WINDOW_SIZE - 1 {
+            break;
+        }
+        let length = data[start..]
+            .iter()
+            .zip(&data[position..position + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best_length {
+            (best_length, best_distance) = (length, distance);
+            if length == max_length {
+                break;
+            }
+        }
+        let next = previous[start % WINDOW_SIZE];
+        // Stop once the chain wraps around to newer positions.
+        if next > candidate {
+            break;
+        }
+        candidate = next;
+    }
+    (best_length, best_distance)
+}
+
+#[cfg(test)]
+mod zlib_tests {
+    use super::*;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn squares() -> Vec<u8> {
+        (0..200u32)
+            .map(|i| format!("{},", i * i))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(&squares()), 3_179_270_053);
+    }
+
+    #[test]
+    fn test_decompress_stored_block() {
+        let stream = from_hex("7801010c00f3ff73746f72656420626c6f636b1f8004bd");
+        assert_eq!(decompress(&stream).unwrap(), b"stored block");
+    }
+
+    #[test]
+    fn test_decompress_fixed_block() {
+        let stream = from_hex("78dacb48cdc9c957c8402701680308b1");
+        assert_eq!(decompress(&stream).unwrap(), b"hello hello hello hello");
+    }
+
+    #[test]
+    fn test_decompress_dynamic_block() {
+        let stream = from_hex(concat!(
+            "78da25934902042108033fe4411641feffb1aed8976946594212f7b2956b96d5f2b3a256ceaa5cd796",
+            "edbdccf966724bc690428e1fbe775678924f3579997c6faee3b34ed72af28a6ff3bf39bfdc0f7953ea",
+            "4ba16d3a98d1cad4d39ce6169a921a7734b704a084a4416437850184be85c284d8f72c8f9dcb731bc8",
+            "a8f052d43abbba1df24215417f810679d02f92cea119d14c8bcbdc1810e4064b0a5506f832419a05e6",
+            "6c6dab35ce669f635a38b43137eb68e7d38a86b3dadc5265b049451d6aabe952977ebd8fc8a1a299b9",
+            "fa30b71b043d60b9a2fe3af86e4a8b02f3bda01fed4106112861932de7eea795c8dafee2dc22b8c880",
+            "6692cd5467e6a138c5a39528b52b76fd573ac4b91fd1ef2d257c9e284f9fc8f304926a311230ad9e37",
+            "540b4ac5f74a3aeaf80d88b0c7899dabb8de798572eae597a8b47e7d3ad5b35fff1ecdba6f2ea22b7e",
+            "78660bdb3c9cf3cc31c2cfceece25b7bf96e19c47e87842c62e2c1558c5dc48fff3671f1e6613a8f7c",
+            "3e6ae5e7abcd509f14f33846fd8ff4c0fbaa3d52c95197f392760e70f29fa2de472fa4a5b3b3858c28",
+            "f57933aa1d79c2e7c00304c309e66c59545cc51ec52657819e9c70790ddbcaa82e0746985e5ec89711",
+            "b4c6ca9a8b44a3581e8e13b2f379ce2ee98be59fdfafacdfef1574e941dcf73678a08af5629077bfa7",
+            "003f1fbd7fcfa5",
+        ));
+        assert_eq!(decompress(&stream).unwrap(), squares());
+    }
+
+    #[test]
+    fn test_compress_round_trip() {
+        let long_run = vec![b'a'; 100_000];
+        let mixed: Vec<u8> = (0..70_000u64).map(|i| (i * i % 251) as u8).collect();
+        for data in [
+            &b""[..],
+            b"a",
+            b"abcabcabcabc",
+            &squares(),
+            &long_run,
+            &mixed,
+        ] {
+            assert_eq!(decompress(&compress(data)).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn test_compress_shrinks_repetitive_data() {
+        let data = b"<rdf:Description rdf:about=''/>".repeat(100);
+        assert!(compress(&data).len() < data.len() / 10);
+    }
+
+    #[test]
+    fn test_decompress_rejects_bad_streams() {
+        let mut stream = compress(b"some text");
+        let last = stream.len() - 1;
+        stream[last] ^= 1;
+        assert!(matches!(
+            decompress(&stream),
+            Err(PngError::InvalidZlibStream("Adler-32 checksum mismatch"))
+        ));
+
+        let stream = compress(b"some text");
+        assert!(decompress(&stream[..stream.len() - 6]).is_err());
+        assert!(decompress(&[0x78, 0x9D]).is_err());
+        assert!(decompress(&[0x79, 0x9C]).is_err());
+        assert!(decompress(&[]).is_err());
+    }
+}